@@ -2,28 +2,321 @@ use chrono::NaiveDateTime;
 use serde::{Serialize, Deserialize};
 use strum::{FromRepr, EnumIter};
 
+pub mod search;
+
+#[derive(Serialize, Deserialize, FromRepr, EnumIter, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[repr(i16)]
+pub enum DrawStrategyKind {
+    Uniform = 0,
+    #[default]
+    Weighted,
+    ShuffleBag
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CreateUpdateBag {
+    pub name: String,
+    #[serde(default)]
+    pub draw_strategy: Option<DrawStrategyKind>,
+    /// put every drawn item back into the bag once nothing is left to draw
+    #[serde(default)]
+    pub auto_restock: bool,
+    /// dice rolled for the rounds of items that set none of their own, like `1d4+1`
+    #[serde(default)]
+    pub rounds_dice: Option<String>,
+    /// how many draws can be active at once, by default the configured hand size
+    #[serde(default)]
+    pub hand_size: Option<i16>,
+    /// how many draws can be rerolled in a game, by default the configured
+    /// budget; unlimited when neither sets one
+    #[serde(default)]
+    pub reroll_budget: Option<i16>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Bag {
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub id: i32,
+    pub name: String,
+    pub draw_strategy: Option<DrawStrategyKind>,
+    pub auto_restock: bool,
+    pub rounds_dice: Option<String>,
+    pub hand_size: Option<i16>,
+    pub reroll_budget: Option<i16>
+}
+
+#[derive(Serialize, Deserialize, FromRepr, EnumIter, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(i16)]
+pub enum BagRole {
+    Viewer = 0,
+    Player,
+    GameMaster,
+    Owner
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BagMember {
+    pub user_pid: String,
+    pub name: String,
+    pub role: BagRole
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UpdateBagMember {
+    pub role: BagRole
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CreateInvite {
+    pub role: BagRole
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Invite {
+    pub created_at: NaiveDateTime,
+    pub bag_id: i32,
+    pub token: String,
+    pub role: BagRole,
+    pub accepted_at: Option<NaiveDateTime>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AcceptInvite {
+    pub token: String
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CreateUpdateItem {
     pub name: String,
     pub description: Option<String>,
     pub quantity: i32,
     pub size: ItemSize,
-    pub infinite: bool
+    pub infinite: bool,
+    #[serde(default = "default_weight")]
+    pub weight: i32,
+    /// names of the tags of the item; unknown tags are created in the bag
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// how many rounds the item lasts once drawn, by default the range
+    /// configured for its size
+    #[serde(default)]
+    pub rounds: Option<RoundRange>,
+    /// dice rolled for the rounds of the item instead, like `2d6 keep highest`
+    #[serde(default)]
+    pub rounds_dice: Option<String>
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Default for CreateUpdateItem {
+    /// A single small item with no description, tags or rounds of its own
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description: None,
+            quantity: 1,
+            size: ItemSize::Small,
+            infinite: false,
+            weight: default_weight(),
+            tags: vec![],
+            rounds: None,
+            rounds_dice: None
+        }
+    }
+}
+
+/// Bounds of how many rounds a drawn item lasts; equal bounds make a fixed duration
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoundRange {
+    pub min: i16,
+    pub max: i16
+}
+
+const fn default_weight() -> i32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Item {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub id: i32,
+    pub bag_id: i32,
     pub name: String,
     pub description: Option<String>,
     pub quantity: i32,
     pub size: ItemSize,
-    pub infinite: bool
+    pub infinite: bool,
+    pub weight: i32,
+    pub tags: Vec<String>,
+    /// when the item was archived, archived items are hidden from listings and draws
+    pub deleted_at: Option<NaiveDateTime>,
+    /// bumped on every change, sent as the `ETag` of the item
+    pub version: i32,
+    /// how many rounds the item lasts once drawn, `None` when it follows its size
+    pub rounds: Option<RoundRange>,
+    /// dice rolled for the rounds of the item, before its `rounds`
+    pub rounds_dice: Option<String>,
+    /// stock left in the ledger of the item, `None` for infinite items
+    pub remaining: Option<i32>,
+    /// how many times the item was ever drawn
+    pub times_drawn: i64,
+    pub last_drawn_at: Option<NaiveDateTime>
+}
+
+impl From<Item> for CreateUpdateItem {
+    fn from(value: Item) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            quantity: value.quantity,
+            size: value.size,
+            infinite: value.infinite,
+            weight: value.weight,
+            tags: value.tags,
+            rounds: value.rounds,
+            rounds_dice: value.rounds_dice
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, FromRepr, EnumIter, Clone, Debug)]
+/// Changes to some fields of an item, fields left out keep their value
+// a missing field, `null` and a value are three different changes
+#[allow(clippy::option_option)]
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct PatchItem {
+    pub name: Option<String>,
+    /// `null` clears the description
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option::deserialize")]
+    pub description: Option<Option<String>>,
+    pub quantity: Option<i32>,
+    pub size: Option<ItemSize>,
+    pub infinite: Option<bool>,
+    pub weight: Option<i32>,
+    /// replaces every tag of the item
+    pub tags: Option<Vec<String>>,
+    /// `null` makes the item follow the rounds of its size again
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option::deserialize")]
+    pub rounds: Option<Option<RoundRange>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option::deserialize")]
+    pub rounds_dice: Option<Option<String>>
+}
+
+impl CreateUpdateItem {
+    /// Applies the changes in `patch` to the item.
+    #[must_use]
+    pub fn patched(self, patch: PatchItem) -> Self {
+        Self {
+            name: patch.name.unwrap_or(self.name),
+            description: patch.description.unwrap_or(self.description),
+            quantity: patch.quantity.unwrap_or(self.quantity),
+            size: patch.size.unwrap_or(self.size),
+            infinite: patch.infinite.unwrap_or(self.infinite),
+            weight: patch.weight.unwrap_or(self.weight),
+            tags: patch.tags.unwrap_or(self.tags),
+            rounds: patch.rounds.unwrap_or(self.rounds),
+            rounds_dice: patch.rounds_dice.unwrap_or(self.rounds_dice)
+        }
+    }
+}
+
+/// Tells a field set to `null` apart from a missing one, missing fields are
+/// left out when serializing so the default serializer already round-trips
+mod double_option {
+    use serde::{Deserialize, Deserializer};
+
+    #[allow(clippy::option_option)]
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<T>>, D::Error> {
+        Option::deserialize(deserializer).map(Some)
+    }
+}
+
+/// File formats items are imported from and exported to
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ItemFileFormat {
+    Csv,
+    #[default]
+    Json,
+    Yaml
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ItemImportOptions {
+    #[serde(default)]
+    pub format: ItemFileFormat,
+    /// check the file and report what would change without saving anything
+    #[serde(default)]
+    pub dry_run: bool,
+    /// update the item of the bag with the same name instead of adding a new one
+    #[serde(default)]
+    pub upsert: bool
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ItemExportOptions {
+    #[serde(default)]
+    pub format: ItemFileFormat
+}
+
+/// Problems found in one row of an import, rows are numbered from 1
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ImportRowError {
+    pub row: usize,
+    pub errors: Vec<String>
+}
+
+/// Outcome of an item import; nothing is saved on a dry run or when any row has errors
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ItemImportReport {
+    pub dry_run: bool,
+    pub created: u64,
+    pub updated: u64,
+    pub errors: Vec<ImportRowError>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CreateUpdateTag {
+    pub name: String
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Tag {
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub id: i32,
+    pub bag_id: i32,
+    pub name: String
+}
+
+/// Restricts items by tag names, given as comma separated lists in query strings
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct TagFilter {
+    /// items with at least one of these tags
+    #[serde(default, with = "comma_separated")]
+    pub tags: Vec<String>,
+    /// items with none of these tags
+    #[serde(default, with = "comma_separated")]
+    pub exclude_tags: Vec<String>
+}
+
+mod comma_separated {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(values: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&values.join(","))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+        let values = String::deserialize(deserializer)?;
+        Ok(values
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToString::to_string)
+            .collect())
+    }
+}
+
+#[derive(Serialize, Deserialize, FromRepr, EnumIter, Clone, Debug, PartialEq, Eq)]
 #[repr(i16)]
 pub enum ItemSize {
     Small = 0,
@@ -31,37 +324,341 @@ pub enum ItemSize {
     Large
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ItemSort {
+    #[default]
+    CreatedAt,
+    Name,
+    Quantity,
+    TimesDrawn
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc
+}
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct ItemFilter {
+    /// text to look for in the name or description, ignoring case and accents
+    pub q: Option<String>,
     pub name: Option<String>,
     pub description: Option<String>,
     pub size: Option<ItemSize>,
     pub infinite: Option<bool>,
+    /// items with at least one of these tags
+    #[serde(default, with = "comma_separated")]
+    pub tags: Vec<String>,
+    /// items with none of these tags
+    #[serde(default, with = "comma_separated")]
+    pub exclude_tags: Vec<String>,
+    /// list the archived items instead of the ones in the bag
+    #[serde(default)]
+    pub archived: bool,
+    /// only items that can still be drawn
+    #[serde(default)]
+    pub available: bool,
+    #[serde(default)]
+    pub sort: ItemSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    #[serde(default)]
     pub page_num: u64,
+    #[serde(default)]
     pub page_size: u64
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SizeFacet {
+    pub size: ItemSize,
+    pub count: u64
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct InfiniteFacet {
+    pub infinite: bool,
+    pub count: u64
+}
+
+/// Number of items matching the filter, per size and per infinite flag
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ItemFacets {
+    pub sizes: Vec<SizeFacet>,
+    pub infinite: Vec<InfiniteFacet>
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ItemPage {
     pub items: Vec<Item>,
     pub page_num: u64,
     pub total_pages: u64,
     pub page_size: u64,
-    pub total_results: u64
+    pub total_results: u64,
+    pub facets: ItemFacets
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct TakenItem {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub id: i32,
+    pub bag_id: i32,
     pub item_id: i32,
     pub rounds_left: i16,
-    pub done: bool,
-    pub rounds_total: i16
+    pub status: TakenStatus,
+    pub rounds_total: i16,
+    /// how `rounds_total` was rolled, when it came from dice
+    pub rounds_roll: Option<DiceRoll>
+}
+
+/// Where a draw is in its life
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TakenStatus {
+    /// in the hand, losing a round each round
+    Active,
+    /// in the hand, but sitting out rounds until it is resumed
+    Paused,
+    /// out of rounds or finished by hand
+    Done,
+    /// rejected, with its item put back into the bag
+    Rerolled
 }
 
+/// Changes the rounds left on a draw by `delta`, which may be negative
 #[derive(Serialize, Deserialize)]
+pub struct AdjustRounds {
+    pub delta: i16
+}
+
+/// The dice rolled for the rounds of a draw
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DiceRoll {
+    /// the expression rolled, written like `2d6kh1+1`
+    pub expression: String,
+    /// the rolls of each group of dice, in the order of the expression
+    pub dice: Vec<DiceRolls>,
+    pub total: i32
+}
+
+/// The rolls of one group of dice of an expression, like `2d6kh1`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DiceRolls {
+    pub dice: String,
+    pub rolls: Vec<u32>,
+    /// the rolls that count towards the total
+    pub kept: Vec<u32>
+}
+
+/// The round the game played with a bag is in
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GameRound {
+    pub bag_id: i32,
+    /// 1 until a new game is started
+    pub game: i32,
+    /// 0 until the first round of the game is advanced
+    pub round: i32,
+    /// when the round began, `None` before the first round of the first game
+    pub started_at: Option<NaiveDateTime>
+}
+
+/// What advancing a bag to its next round did to its active draws
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RoundSummary {
+    pub round: GameRound,
+    /// draws that are still active, with a round less left
+    pub active: Vec<TakenItem>,
+    /// draws that ran out of rounds and are now done
+    pub expired: Vec<ExpiredDraw>
+}
+
+/// A draw that ran out of rounds, with the item that was drawn
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExpiredDraw {
+    pub taken: TakenItem,
+    pub item: Item
+}
+
+/// A change to the draws of a bag that can be undone and redone
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DrawTransition {
+    pub created_at: NaiveDateTime,
+    pub id: i32,
+    pub bag_id: i32,
+    /// what was done to the draws, like `Decrement`, `Done` or `Advance`
+    pub action: AuditAction,
+    /// whether the change is undone and can be redone
+    pub undone: bool,
+    /// the draws the change touched, as they are now
+    pub taken: Vec<TakenItem>
+}
+
+/// Body of the 409 returned when drawing from a bag that has nothing left
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct BagExhausted {
+    pub error: String,
+    pub bag_id: i32,
+    pub description: String
+}
+
+/// Body of the 412 returned when updating an item that changed since the
+/// version given in `If-Match`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct VersionMismatch {
+    pub error: String,
+    pub item_id: i32,
+    pub current_version: i32,
+    pub description: String
+}
+
+/// What changed the stock of an item
+#[derive(Serialize, Deserialize, FromRepr, EnumIter, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i16)]
+pub enum InventoryKind {
+    /// items put in the bag, or a count corrected upwards
+    Add = 0,
+    /// items taken out of the bag, or a count corrected downwards
+    Remove,
+    Draw,
+    /// the stock refilled up to the quantity of the item
+    Restock,
+    /// a drawn item put back by a reroll
+    Reroll
+}
+
+/// A change to the stock of an item in its ledger
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InventoryEntry {
+    pub created_at: NaiveDateTime,
+    pub id: i32,
+    pub item_id: i32,
+    pub kind: InventoryKind,
+    pub delta: i32,
+    pub reason: Option<String>,
+    /// the draw that took the item, for draws
+    pub taken_item_id: Option<i32>
+}
+
+/// Refills the stock of an item
+#[derive(Serialize, Deserialize, Default)]
+pub struct Restock {
+    /// how many items to put back, by default enough to get back to the quantity of the item
+    pub amount: Option<i32>,
+    pub reason: Option<String>
+}
+
+/// Corrects the stock of an item by `delta`, which may be negative
+#[derive(Serialize, Deserialize)]
+pub struct AdjustStock {
+    pub delta: i32,
+    pub reason: String
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct TakenItemHistoryFilter {
+    pub item_id: Option<i32>,
+    /// only draws made at or after this time
+    pub from: Option<NaiveDateTime>,
+    /// only draws made before this time
+    pub to: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub page_num: u64,
+    #[serde(default)]
+    pub page_size: u64
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DrawnBy {
+    pub user_pid: String,
+    pub name: String
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TakenItemHistoryEntry {
+    pub taken: TakenItem,
+    pub item: Item,
+    /// `None` for draws made before draws were attributed to users
+    pub drawn_by: Option<DrawnBy>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct TakenItemHistory {
-    taken_history: Vec<TakenItem>
-}
\ No newline at end of file
+    pub taken_history: Vec<TakenItemHistoryEntry>,
+    pub page_num: u64,
+    pub total_pages: u64,
+    pub page_size: u64,
+    pub total_results: u64
+}
+#[derive(Serialize, Deserialize, FromRepr, EnumIter, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i16)]
+pub enum AuditAction {
+    Create = 0,
+    Update,
+    Archive,
+    Restore,
+    Purge,
+    Draw,
+    Decrement,
+    Done,
+    Restock,
+    Adjust,
+    Advance,
+    Undo,
+    Redo,
+    Pause,
+    Resume,
+    Reroll,
+    NewGame
+}
+
+#[derive(Serialize, Deserialize, FromRepr, EnumIter, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i16)]
+pub enum AuditEntity {
+    Item = 0,
+    TakenItem,
+    Bag,
+    InventoryEntry,
+    GameRound
+}
+
+/// A change made to an item or a draw, with the entity as it was before and after
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditEvent {
+    pub created_at: NaiveDateTime,
+    pub id: i32,
+    pub bag_id: i32,
+    /// `None` for changes made by tasks rather than users
+    pub actor_pid: Option<String>,
+    pub action: AuditAction,
+    pub entity: AuditEntity,
+    pub entity_id: i32,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct AuditFilter {
+    pub bag_id: Option<i32>,
+    /// pid of the user who made the changes
+    pub actor: Option<String>,
+    pub entity: Option<AuditEntity>,
+    pub entity_id: Option<i32>,
+    /// only changes made at or after this time
+    pub from: Option<NaiveDateTime>,
+    /// only changes made before this time
+    pub to: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub page_num: u64,
+    #[serde(default)]
+    pub page_size: u64
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditPage {
+    pub events: Vec<AuditEvent>,
+    pub page_num: u64,
+    pub total_pages: u64,
+    pub page_size: u64,
+    pub total_results: u64
+}
@@ -0,0 +1,13 @@
+//! Text normalization shared by the server and the database migrations, so
+//! searches behave the same on every database backend.
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Folds `text` into the form stored in the search columns: lower case with
+/// accents removed, so `Épée` and `epee` match.
+#[must_use]
+pub fn search_key(text: &str) -> String {
+    text.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
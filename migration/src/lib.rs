@@ -6,6 +6,27 @@ mod m20220101_000001_users;
 mod m20240123_152813_items;
 mod m20240123_154524_taken_items;
 mod m20240129_160019_add_taken_starting_rounds;
+mod m20240205_101512_bags;
+mod m20240209_143020_bag_members;
+mod m20240212_091145_item_weight;
+mod m20240214_170402_bag_draw_strategy;
+mod m20240216_102233_auto_restock;
+mod m20240219_093012_single_active_draw;
+mod m20240221_141207_taken_drawn_by;
+mod m20240223_110530_item_search;
+mod m20240226_150812_tags;
+mod m20240228_094417_item_archive;
+mod m20240301_103215_audit_events;
+mod m20240304_142518_item_version;
+mod m20240306_091540_inventory_ledger;
+mod m20240308_160245_item_rounds;
+mod m20240311_104730_rounds_dice;
+mod m20240313_152904_draw_hands;
+mod m20240315_100318_game_rounds;
+mod m20240318_093244_draw_transitions;
+mod m20240320_141052_taken_paused;
+mod m20240322_101544_rerolls;
+mod m20240325_094120_game_sessions;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -16,6 +37,27 @@ impl MigratorTrait for Migrator {
             Box::new(m20240123_152813_items::Migration),
             Box::new(m20240123_154524_taken_items::Migration),
             Box::new(m20240129_160019_add_taken_starting_rounds::Migration),
+            Box::new(m20240205_101512_bags::Migration),
+            Box::new(m20240209_143020_bag_members::Migration),
+            Box::new(m20240212_091145_item_weight::Migration),
+            Box::new(m20240214_170402_bag_draw_strategy::Migration),
+            Box::new(m20240216_102233_auto_restock::Migration),
+            Box::new(m20240219_093012_single_active_draw::Migration),
+            Box::new(m20240221_141207_taken_drawn_by::Migration),
+            Box::new(m20240223_110530_item_search::Migration),
+            Box::new(m20240226_150812_tags::Migration),
+            Box::new(m20240228_094417_item_archive::Migration),
+            Box::new(m20240301_103215_audit_events::Migration),
+            Box::new(m20240304_142518_item_version::Migration),
+            Box::new(m20240306_091540_inventory_ledger::Migration),
+            Box::new(m20240308_160245_item_rounds::Migration),
+            Box::new(m20240311_104730_rounds_dice::Migration),
+            Box::new(m20240313_152904_draw_hands::Migration),
+            Box::new(m20240315_100318_game_rounds::Migration),
+            Box::new(m20240318_093244_draw_transitions::Migration),
+            Box::new(m20240320_141052_taken_paused::Migration),
+            Box::new(m20240322_101544_rerolls::Migration),
+            Box::new(m20240325_094120_game_sessions::Migration),
         ]
     }
 }
\ No newline at end of file
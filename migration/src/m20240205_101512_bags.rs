@@ -0,0 +1,272 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DatabaseBackend, Statement};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    Id,
+    Name,
+    UserId,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Items {
+    Table,
+    Id,
+    Name,
+    Description,
+    Quantity,
+    Size,
+    Infinite,
+    BagId,
+}
+
+#[derive(DeriveIden)]
+enum TakenItems {
+    Table,
+    ItemId,
+    RoundsLeft,
+    Done,
+    RoundsTotal,
+    BagId,
+}
+
+const ITEM_COLUMNS: &str = "created_at, updated_at, id, name, description, quantity, size, infinite";
+const TAKEN_COLUMNS: &str = "created_at, updated_at, id, item_id, rounds_left, done, rounds_total";
+
+fn items_table(with_bag: bool) -> TableCreateStatement {
+    let mut table = table_auto(Alias::new("items_new"));
+    table
+        .col(pk_auto(Items::Id).borrow_mut())
+        .col(string(Items::Name).borrow_mut())
+        .col(text_null(Items::Description).borrow_mut())
+        .col(integer(Items::Quantity).borrow_mut())
+        .col(tiny_integer(Items::Size).borrow_mut())
+        .col(bool(Items::Infinite).default(false).borrow_mut());
+    if with_bag {
+        table.col(integer(Items::BagId).borrow_mut()).foreign_key(
+            ForeignKey::create()
+                .name("fk-items-bags")
+                .from(Alias::new("items_new"), Items::BagId)
+                .to(Bags::Table, Bags::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade),
+        );
+    }
+    table.take()
+}
+
+fn taken_items_table(with_bag: bool) -> TableCreateStatement {
+    let mut table = table_auto(Alias::new("taken_items_new"));
+    table
+        .col(pk_auto(Items::Id).borrow_mut())
+        .col(integer(TakenItems::ItemId).borrow_mut())
+        .col(tiny_integer(TakenItems::RoundsLeft).borrow_mut())
+        .col(bool(TakenItems::Done).default(false).borrow_mut())
+        .col(tiny_integer(TakenItems::RoundsTotal).borrow_mut())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-taken_items-items")
+                .from(Alias::new("taken_items_new"), TakenItems::ItemId)
+                .to(Alias::new("items_new"), Items::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade),
+        );
+    if with_bag {
+        table.col(integer(TakenItems::BagId).borrow_mut()).foreign_key(
+            ForeignKey::create()
+                .name("fk-taken_items-bags")
+                .from(Alias::new("taken_items_new"), TakenItems::BagId)
+                .to(Bags::Table, Bags::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade),
+        );
+    }
+    table.take()
+}
+
+/// `SQLite` can neither alter a column nor add a foreign key to an existing
+/// table, so `items` and `taken_items` are rebuilt with or without a required
+/// `bag_id`. The new draws table points at the new items table until both
+/// are renamed, so dropping the old tables cascades into nothing.
+async fn rebuild_sqlite(manager: &SchemaManager<'_>, with_bag: bool) -> Result<(), DbErr> {
+    let (items, taken) = if with_bag {
+        (format!("{ITEM_COLUMNS}, bag_id"), format!("{TAKEN_COLUMNS}, bag_id"))
+    } else {
+        (ITEM_COLUMNS.to_string(), TAKEN_COLUMNS.to_string())
+    };
+
+    manager.create_table(items_table(with_bag)).await?;
+    manager.create_table(taken_items_table(with_bag)).await?;
+
+    let db = manager.get_connection();
+    db.execute_unprepared(&format!("INSERT INTO items_new ({items}) SELECT {items} FROM items"))
+        .await?;
+    db.execute_unprepared(&format!(
+        "INSERT INTO taken_items_new ({taken}) SELECT {taken} FROM taken_items"
+    ))
+    .await?;
+    db.execute_unprepared("DROP TABLE taken_items").await?;
+    db.execute_unprepared("DROP TABLE items").await?;
+    db.execute_unprepared("ALTER TABLE items_new RENAME TO items").await?;
+    db.execute_unprepared("ALTER TABLE taken_items_new RENAME TO taken_items")
+        .await?;
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Items and draws created before bags existed all lived in one shared
+        // bag owned by the first registered user; without a user there is no
+        // one to own it and `bag_id` could never be filled in.
+        let db = manager.get_connection();
+        let orphaned = db
+            .query_one(Statement::from_string(
+                manager.get_database_backend(),
+                "SELECT 1 AS orphaned FROM items WHERE NOT EXISTS (SELECT 1 FROM users) LIMIT 1",
+            ))
+            .await?;
+        if orphaned.is_some() {
+            return Err(DbErr::Migration(
+                "items exist but there are no users to own the default bag; register a user \
+                 before running this migration"
+                    .to_string(),
+            ));
+        }
+
+        manager
+            .create_table(
+                table_auto(Bags::Table)
+                    .col(pk_auto(Bags::Id).borrow_mut())
+                    .col(string(Bags::Name).borrow_mut())
+                    .col(integer(Bags::UserId).borrow_mut())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bags-users")
+                            .from(Bags::Table, Bags::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Items::Table)
+                    .add_column(integer_null(Items::BagId).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .add_column(integer_null(TakenItems::BagId).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        db.execute_unprepared(
+            "INSERT INTO bags (name, user_id) \
+             SELECT 'Default bag', MIN(id) FROM users \
+             WHERE EXISTS (SELECT 1 FROM items) \
+             HAVING MIN(id) IS NOT NULL",
+        )
+        .await?;
+        db.execute_unprepared("UPDATE items SET bag_id = (SELECT MIN(id) FROM bags) WHERE bag_id IS NULL")
+            .await?;
+        db.execute_unprepared("UPDATE taken_items SET bag_id = (SELECT bag_id FROM items WHERE items.id = taken_items.item_id) WHERE bag_id IS NULL")
+            .await?;
+
+        if manager.get_database_backend() == DatabaseBackend::Sqlite {
+            rebuild_sqlite(manager, true).await?;
+        } else {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Items::Table)
+                        .modify_column(integer(Items::BagId).borrow_mut())
+                        .add_foreign_key(
+                            TableForeignKey::new()
+                                .name("fk-items-bags")
+                                .from_tbl(Items::Table)
+                                .from_col(Items::BagId)
+                                .to_tbl(Bags::Table)
+                                .to_col(Bags::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned()
+                )
+                .await?;
+
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(TakenItems::Table)
+                        .modify_column(integer(TakenItems::BagId).borrow_mut())
+                        .add_foreign_key(
+                            TableForeignKey::new()
+                                .name("fk-taken_items-bags")
+                                .from_tbl(TakenItems::Table)
+                                .from_col(TakenItems::BagId)
+                                .to_tbl(Bags::Table)
+                                .to_col(Bags::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned()
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DatabaseBackend::Sqlite {
+            rebuild_sqlite(manager, false).await?;
+        } else {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(TakenItems::Table)
+                        .drop_foreign_key(Alias::new("fk-taken_items-bags"))
+                        .drop_column(TakenItems::BagId)
+                        .to_owned()
+                )
+                .await?;
+
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Items::Table)
+                        .drop_foreign_key(Alias::new("fk-items-bags"))
+                        .drop_column(Items::BagId)
+                        .to_owned()
+                )
+                .await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(Bags::Table).to_owned())
+            .await
+    }
+}
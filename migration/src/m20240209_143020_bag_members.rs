@@ -0,0 +1,128 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum BagMembers {
+    Table,
+    Id,
+    BagId,
+    UserId,
+    Role,
+}
+
+#[derive(DeriveIden)]
+enum BagInvites {
+    Table,
+    Id,
+    BagId,
+    Token,
+    Role,
+    InvitedBy,
+    AcceptedBy,
+    AcceptedAt,
+}
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto(BagMembers::Table)
+                    .col(pk_auto(BagMembers::Id).borrow_mut())
+                    .col(integer(BagMembers::BagId).borrow_mut())
+                    .col(integer(BagMembers::UserId).borrow_mut())
+                    .col(tiny_integer(BagMembers::Role).borrow_mut())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bag_members-bags")
+                            .from(BagMembers::Table, BagMembers::BagId)
+                            .to(Bags::Table, Bags::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bag_members-users")
+                            .from(BagMembers::Table, BagMembers::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .unique()
+                            .name("idx-bag_members-bag_id-user_id")
+                            .col(BagMembers::BagId)
+                            .col(BagMembers::UserId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                table_auto(BagInvites::Table)
+                    .col(pk_auto(BagInvites::Id).borrow_mut())
+                    .col(integer(BagInvites::BagId).borrow_mut())
+                    .col(string_uniq(BagInvites::Token).borrow_mut())
+                    .col(tiny_integer(BagInvites::Role).borrow_mut())
+                    .col(integer(BagInvites::InvitedBy).borrow_mut())
+                    .col(integer_null(BagInvites::AcceptedBy).borrow_mut())
+                    .col(timestamp_null(BagInvites::AcceptedAt).borrow_mut())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bag_invites-bags")
+                            .from(BagInvites::Table, BagInvites::BagId)
+                            .to(Bags::Table, Bags::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bag_invites-users")
+                            .from(BagInvites::Table, BagInvites::InvitedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every existing bag is owned by the user that created it.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT INTO bag_members (bag_id, user_id, role) SELECT id, user_id, 3 FROM bags",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BagInvites::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(BagMembers::Table).to_owned())
+            .await
+    }
+}
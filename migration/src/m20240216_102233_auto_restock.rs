@@ -0,0 +1,63 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    AutoRestock,
+}
+
+#[derive(DeriveIden)]
+enum TakenItems {
+    Table,
+    Restocked,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bags::Table)
+                    .add_column(bool(Bags::AutoRestock).default(false).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        // draws that were put back into the bag no longer count against the item quantity
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .add_column(bool(TakenItems::Restocked).default(false).borrow_mut())
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .drop_column(TakenItems::Restocked)
+                    .to_owned()
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bags::Table)
+                    .drop_column(Bags::AutoRestock)
+                    .to_owned()
+            )
+            .await
+    }
+}
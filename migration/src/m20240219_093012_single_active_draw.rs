@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum TakenItems {
+    Table,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Concurrent draws may already have left several active draws in a
+        // bag; keep the latest one.
+        db.execute_unprepared(
+            "UPDATE taken_items SET done = true WHERE done = false AND id NOT IN \
+             (SELECT MAX(id) FROM taken_items WHERE done = false GROUP BY bag_id)",
+        )
+        .await?;
+
+        // sea-query cannot build partial indexes yet; this statement is the
+        // same on Postgres and SQLite.
+        db.execute_unprepared(
+            "CREATE UNIQUE INDEX \"idx-taken_items-bag_id-active\" ON taken_items (bag_id) WHERE done = false",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-taken_items-bag_id-active")
+                    .table(TakenItems::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
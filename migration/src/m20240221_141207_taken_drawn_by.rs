@@ -0,0 +1,80 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DatabaseBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum TakenItems {
+    Table,
+    DrawnBy,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Nobody knows who made the draws recorded so far, so the column stays nullable.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .add_column(integer_null(TakenItems::DrawnBy).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        // SQLite cannot add a foreign key to an existing table
+        if manager.get_database_backend() == DatabaseBackend::Postgres {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(TakenItems::Table)
+                        .add_foreign_key(
+                            TableForeignKey::new()
+                                .name("fk-taken_items-users")
+                                .from_tbl(TakenItems::Table)
+                                .from_col(TakenItems::DrawnBy)
+                                .to_tbl(Users::Table)
+                                .to_col(Users::Id)
+                                .on_delete(ForeignKeyAction::SetNull)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned()
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DatabaseBackend::Postgres {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(TakenItems::Table)
+                        .drop_foreign_key(Alias::new("fk-taken_items-users"))
+                        .to_owned()
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .drop_column(TakenItems::DrawnBy)
+                    .to_owned()
+            )
+            .await
+    }
+}
@@ -0,0 +1,93 @@
+use std::borrow::BorrowMut;
+
+use interface::search::search_key;
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Items {
+    Table,
+    Id,
+    Name,
+    Description,
+    SearchName,
+    SearchDescription,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Items::Table)
+                    .add_column(string(Items::SearchName).default("").borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Items::Table)
+                    .add_column(text_null(Items::SearchDescription).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        // The search columns are folded in Rust, as neither database can strip
+        // accents the same way, so existing items are filled in row by row.
+        let db = manager.get_connection();
+        let rows = db
+            .query_all(
+                db.get_database_backend().build(
+                    Query::select()
+                        .columns([Items::Id, Items::Name, Items::Description])
+                        .from(Items::Table),
+                ),
+            )
+            .await?;
+        for row in rows {
+            let id: i32 = row.try_get("", "id")?;
+            let name: String = row.try_get("", "name")?;
+            let description: Option<String> = row.try_get("", "description")?;
+            manager
+                .exec_stmt(
+                    Query::update()
+                        .table(Items::Table)
+                        .values([
+                            (Items::SearchName, search_key(&name).into()),
+                            (Items::SearchDescription, description.as_deref().map(search_key).into()),
+                        ])
+                        .and_where(Expr::col(Items::Id).eq(id))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Items::Table)
+                    .drop_column(Items::SearchDescription)
+                    .to_owned()
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Items::Table)
+                    .drop_column(Items::SearchName)
+                    .to_owned()
+            )
+            .await
+    }
+}
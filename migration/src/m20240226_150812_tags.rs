@@ -0,0 +1,107 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Tags {
+    Table,
+    Id,
+    BagId,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum ItemTags {
+    Table,
+    Id,
+    ItemId,
+    TagId,
+}
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Items {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto(Tags::Table)
+                    .col(pk_auto(Tags::Id).borrow_mut())
+                    .col(integer(Tags::BagId).borrow_mut())
+                    .col(string(Tags::Name).borrow_mut())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-tags-bags")
+                            .from(Tags::Table, Tags::BagId)
+                            .to(Bags::Table, Bags::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .unique()
+                            .name("idx-tags-bag_id-name")
+                            .col(Tags::BagId)
+                            .col(Tags::Name),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                table_auto(ItemTags::Table)
+                    .col(pk_auto(ItemTags::Id).borrow_mut())
+                    .col(integer(ItemTags::ItemId).borrow_mut())
+                    .col(integer(ItemTags::TagId).borrow_mut())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-item_tags-items")
+                            .from(ItemTags::Table, ItemTags::ItemId)
+                            .to(Items::Table, Items::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-item_tags-tags")
+                            .from(ItemTags::Table, ItemTags::TagId)
+                            .to(Tags::Table, Tags::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .unique()
+                            .name("idx-item_tags-item_id-tag_id")
+                            .col(ItemTags::ItemId)
+                            .col(ItemTags::TagId),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ItemTags::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Tags::Table).to_owned())
+            .await
+    }
+}
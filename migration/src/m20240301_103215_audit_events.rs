@@ -0,0 +1,59 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum AuditEvents {
+    Table,
+    Id,
+    BagId,
+    ActorPid,
+    Action,
+    Entity,
+    EntityId,
+    Before,
+    After,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign keys: the log outlives the bags, items and users it mentions.
+        manager
+            .create_table(
+                table_auto(AuditEvents::Table)
+                    .col(pk_auto(AuditEvents::Id).borrow_mut())
+                    .col(integer(AuditEvents::BagId).borrow_mut())
+                    .col(string_null(AuditEvents::ActorPid).borrow_mut())
+                    .col(small_integer(AuditEvents::Action).borrow_mut())
+                    .col(small_integer(AuditEvents::Entity).borrow_mut())
+                    .col(integer(AuditEvents::EntityId).borrow_mut())
+                    .col(json_null(AuditEvents::Before).borrow_mut())
+                    .col(json_null(AuditEvents::After).borrow_mut())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-audit_events-bag_id-created_at")
+                    .table(AuditEvents::Table)
+                    .col(AuditEvents::BagId)
+                    .col(AuditEvents::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditEvents::Table).to_owned())
+            .await
+    }
+}
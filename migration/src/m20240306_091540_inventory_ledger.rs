@@ -0,0 +1,161 @@
+use std::borrow::BorrowMut;
+use std::collections::HashMap;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum InventoryEntries {
+    Table,
+    Id,
+    BagId,
+    ItemId,
+    Kind,
+    Delta,
+    Reason,
+    TakenItemId,
+}
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Items {
+    Table,
+    Id,
+    BagId,
+    Quantity,
+}
+
+#[derive(DeriveIden)]
+enum TakenItems {
+    Table,
+    Id,
+    ItemId,
+    Restocked,
+}
+
+/// `interface::InventoryKind::Add`
+const KIND_ADD: i16 = 0;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto(InventoryEntries::Table)
+                    .col(pk_auto(InventoryEntries::Id).borrow_mut())
+                    .col(integer(InventoryEntries::BagId).borrow_mut())
+                    .col(integer(InventoryEntries::ItemId).borrow_mut())
+                    .col(small_integer(InventoryEntries::Kind).borrow_mut())
+                    .col(integer(InventoryEntries::Delta).borrow_mut())
+                    .col(text_null(InventoryEntries::Reason).borrow_mut())
+                    .col(integer_null(InventoryEntries::TakenItemId).borrow_mut())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-inventory_entries-bags")
+                            .from(InventoryEntries::Table, InventoryEntries::BagId)
+                            .to(Bags::Table, Bags::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-inventory_entries-items")
+                            .from(InventoryEntries::Table, InventoryEntries::ItemId)
+                            .to(Items::Table, Items::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-inventory_entries-taken_items")
+                            .from(InventoryEntries::Table, InventoryEntries::TakenItemId)
+                            .to(TakenItems::Table, TakenItems::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-inventory_entries-item_id")
+                    .table(InventoryEntries::Table)
+                    .col(InventoryEntries::ItemId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing items open their ledger with what is left of them: their
+        // quantity minus the draws since their bag was last restocked.
+        let db = manager.get_connection();
+        let builder = db.get_database_backend();
+        let in_use: HashMap<i32, i64> = db
+            .query_all(
+                builder.build(
+                    Query::select()
+                        .column(TakenItems::ItemId)
+                        .expr_as(Expr::col(TakenItems::Id).count(), Alias::new("in_use"))
+                        .from(TakenItems::Table)
+                        .and_where(Expr::col(TakenItems::Restocked).eq(false))
+                        .group_by_col(TakenItems::ItemId),
+                ),
+            )
+            .await?
+            .into_iter()
+            .map(|row| Ok((row.try_get("", "item_id")?, row.try_get("", "in_use")?)))
+            .collect::<Result<_, DbErr>>()?;
+        let items = db
+            .query_all(
+                builder.build(
+                    Query::select()
+                        .columns([Items::Id, Items::BagId, Items::Quantity])
+                        .from(Items::Table),
+                ),
+            )
+            .await?;
+        for row in items {
+            let id: i32 = row.try_get("", "id")?;
+            let bag_id: i32 = row.try_get("", "bag_id")?;
+            let quantity: i32 = row.try_get("", "quantity")?;
+            let in_use = <i32 as TryFrom<i64>>::try_from(in_use.get(&id).copied().unwrap_or(0)).unwrap_or(i32::MAX);
+            manager
+                .exec_stmt(
+                    Query::insert()
+                        .into_table(InventoryEntries::Table)
+                        .columns([
+                            InventoryEntries::BagId,
+                            InventoryEntries::ItemId,
+                            InventoryEntries::Kind,
+                            InventoryEntries::Delta,
+                            InventoryEntries::Reason,
+                        ])
+                        .values_panic([
+                            bag_id.into(),
+                            id.into(),
+                            KIND_ADD.into(),
+                            (quantity - in_use).max(0).into(),
+                            "opening balance".into(),
+                        ])
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(InventoryEntries::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,83 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Items {
+    Table,
+    RoundsDice,
+}
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    RoundsDice,
+}
+
+#[derive(DeriveIden)]
+enum TakenItems {
+    Table,
+    RoundsRoll,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Items::Table)
+                    .add_column(string_null(Items::RoundsDice).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bags::Table)
+                    .add_column(string_null(Bags::RoundsDice).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+        // the dice rolled for draws whose rounds came from an expression
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .add_column(json_null(TakenItems::RoundsRoll).borrow_mut())
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .drop_column(TakenItems::RoundsRoll)
+                    .to_owned()
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bags::Table)
+                    .drop_column(Bags::RoundsDice)
+                    .to_owned()
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Items::Table)
+                    .drop_column(Items::RoundsDice)
+                    .to_owned()
+            )
+            .await
+    }
+}
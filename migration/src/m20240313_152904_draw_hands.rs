@@ -0,0 +1,90 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    HandSize,
+}
+
+#[derive(DeriveIden)]
+enum TakenItems {
+    Table,
+    HandSlot,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bags::Table)
+                    .add_column(small_integer_null(Bags::HandSize).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .add_column(small_integer_null(TakenItems::HandSlot).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        // bags had at most one active draw so far, which takes the first slot
+        db.execute_unprepared("UPDATE taken_items SET hand_slot = 0 WHERE done = false")
+            .await?;
+
+        // An active draw is now unique per slot of the hand rather than per
+        // bag; sea-query cannot build partial indexes yet.
+        db.execute_unprepared("DROP INDEX \"idx-taken_items-bag_id-active\"")
+            .await?;
+        db.execute_unprepared(
+            "CREATE UNIQUE INDEX \"idx-taken_items-bag_id-hand_slot-active\" ON taken_items (bag_id, hand_slot) WHERE done = false",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared("DROP INDEX \"idx-taken_items-bag_id-hand_slot-active\"")
+            .await?;
+        // only the newest draw of each hand stays active
+        db.execute_unprepared(
+            "UPDATE taken_items SET done = true WHERE done = false AND id NOT IN \
+             (SELECT MAX(id) FROM taken_items WHERE done = false GROUP BY bag_id)",
+        )
+        .await?;
+        db.execute_unprepared(
+            "CREATE UNIQUE INDEX \"idx-taken_items-bag_id-active\" ON taken_items (bag_id) WHERE done = false",
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .drop_column(TakenItems::HandSlot)
+                    .to_owned()
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bags::Table)
+                    .drop_column(Bags::HandSize)
+                    .to_owned()
+            )
+            .await
+    }
+}
@@ -0,0 +1,79 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum GameRounds {
+    Table,
+    Id,
+    BagId,
+    Number,
+    AdvancedBy,
+}
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto(GameRounds::Table)
+                    .col(pk_auto(GameRounds::Id).borrow_mut())
+                    .col(integer(GameRounds::BagId).borrow_mut())
+                    .col(integer(GameRounds::Number).borrow_mut())
+                    .col(integer_null(GameRounds::AdvancedBy).borrow_mut())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-game_rounds-bags")
+                            .from(GameRounds::Table, GameRounds::BagId)
+                            .to(Bags::Table, Bags::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-game_rounds-users")
+                            .from(GameRounds::Table, GameRounds::AdvancedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // a round is only reached once, even when it is advanced twice at once
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-game_rounds-bag_id-number")
+                    .table(GameRounds::Table)
+                    .col(GameRounds::BagId)
+                    .col(GameRounds::Number)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GameRounds::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,65 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum DrawTransitions {
+    Table,
+    Id,
+    BagId,
+    Action,
+    Changes,
+    Undone,
+}
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto(DrawTransitions::Table)
+                    .col(pk_auto(DrawTransitions::Id).borrow_mut())
+                    .col(integer(DrawTransitions::BagId).borrow_mut())
+                    .col(small_integer(DrawTransitions::Action).borrow_mut())
+                    .col(json(DrawTransitions::Changes).borrow_mut())
+                    .col(bool(DrawTransitions::Undone).default(false).borrow_mut())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-draw_transitions-bags")
+                            .from(DrawTransitions::Table, DrawTransitions::BagId)
+                            .to(Bags::Table, Bags::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-draw_transitions-bag_id-undone")
+                    .table(DrawTransitions::Table)
+                    .col(DrawTransitions::BagId)
+                    .col(DrawTransitions::Undone)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DrawTransitions::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,63 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Bags {
+    Table,
+    RerollBudget,
+}
+
+#[derive(DeriveIden)]
+enum TakenItems {
+    Table,
+    Rerolled,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bags::Table)
+                    .add_column(small_integer_null(Bags::RerollBudget).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        // rerolled draws put their item back and stay only as history
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .add_column(bool(TakenItems::Rerolled).default(false).borrow_mut())
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .drop_column(TakenItems::Rerolled)
+                    .to_owned()
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bags::Table)
+                    .drop_column(Bags::RerollBudget)
+                    .to_owned()
+            )
+            .await
+    }
+}
@@ -0,0 +1,124 @@
+use std::borrow::BorrowMut;
+
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum GameRounds {
+    Table,
+    BagId,
+    Game,
+    Number,
+}
+
+#[derive(DeriveIden)]
+enum TakenItems {
+    Table,
+    RerolledInGame,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // every bag played one game so far
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(GameRounds::Table)
+                    .add_column(integer(GameRounds::Game).default(1).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        // rounds are numbered again from 0 in each new game
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-game_rounds-bag_id-number")
+                    .table(GameRounds::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-game_rounds-bag_id-game-number")
+                    .table(GameRounds::Table)
+                    .col(GameRounds::BagId)
+                    .col(GameRounds::Game)
+                    .col(GameRounds::Number)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .add_column(integer_null(TakenItems::RerolledInGame).borrow_mut())
+                    .to_owned()
+            )
+            .await?;
+
+        // rerolls that still counted against the budget carry over into the
+        // current game
+        manager
+            .get_connection()
+            .execute_unprepared("UPDATE taken_items SET rerolled_in_game = 1 WHERE rerolled = true AND restocked = false")
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TakenItems::Table)
+                    .drop_column(TakenItems::RerolledInGame)
+                    .to_owned()
+            )
+            .await?;
+
+        // only the rounds of the latest game of each bag fit the old index
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "DELETE FROM game_rounds WHERE game < \
+                 (SELECT MAX(latest.game) FROM game_rounds latest WHERE latest.bag_id = game_rounds.bag_id)",
+            )
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-game_rounds-bag_id-game-number")
+                    .table(GameRounds::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-game_rounds-bag_id-number")
+                    .table(GameRounds::Table)
+                    .col(GameRounds::BagId)
+                    .col(GameRounds::Number)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(GameRounds::Table)
+                    .drop_column(GameRounds::Game)
+                    .to_owned()
+            )
+            .await
+    }
+}
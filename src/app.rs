@@ -12,11 +12,11 @@ use loco_rs::{
 };
 use loco_rs::environment::Environment;
 use migration::Migrator;
-use sea_orm::DatabaseConnection;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend};
 
 use crate::{
     controllers,
-    models::_entities::{users},
+    models::_entities::{audit_events, bag_invites, bag_members, bags, draw_transitions, game_rounds, inventory_entries, item_tags, items, tags, taken_items, users},
     tasks,
     workers::downloader::DownloadWorker,
 };
@@ -44,8 +44,14 @@ impl Hooks for App {
 
     fn routes(_ctx: &AppContext) -> AppRoutes {
         AppRoutes::with_default_routes()
+            .add_route(controllers::bags::routes())
+            .add_route(controllers::members::routes())
+            .add_route(controllers::invites::routes())
             .add_route(controllers::taken::routes())
+            .add_route(controllers::rounds::routes())
             .add_route(controllers::items::routes())
+            .add_route(controllers::tags::routes())
+            .add_route(controllers::audit::routes())
             .prefix("/api")
             //.add_route(controllers::notes::routes())
             .add_route(controllers::auth::routes())
@@ -58,9 +64,24 @@ impl Hooks for App {
 
     fn register_tasks(tasks: &mut Tasks) {
         tasks.register(tasks::seed::SeedData);
+        tasks.register(tasks::items::ImportItems);
+        tasks.register(tasks::items::ExportItems);
+        tasks.register(tasks::items::PurgeItems);
     }
 
     async fn truncate(db: &DatabaseConnection) -> Result<()> {
+        // rows referencing other tables go first
+        truncate_table(db, audit_events::Entity).await?;
+        truncate_table(db, inventory_entries::Entity).await?;
+        truncate_table(db, draw_transitions::Entity).await?;
+        truncate_table(db, game_rounds::Entity).await?;
+        truncate_table(db, taken_items::Entity).await?;
+        truncate_table(db, item_tags::Entity).await?;
+        truncate_table(db, tags::Entity).await?;
+        truncate_table(db, items::Entity).await?;
+        truncate_table(db, bag_invites::Entity).await?;
+        truncate_table(db, bag_members::Entity).await?;
+        truncate_table(db, bags::Entity).await?;
         truncate_table(db, users::Entity).await?;
         //truncate_table(db, notes::Entity).await?;
         Ok(())
@@ -68,7 +89,28 @@ impl Hooks for App {
 
     async fn seed(db: &DatabaseConnection, base: &Path) -> Result<()> {
         db::seed::<users::ActiveModel>(db, &base.join("users.yaml").display().to_string()).await?;
+        db::seed::<bags::ActiveModel>(db, &base.join("bags.yaml").display().to_string()).await?;
+        db::seed::<bag_members::ActiveModel>(db, &base.join("bag_members.yaml").display().to_string()).await?;
+        db::seed::<items::ActiveModel>(db, &base.join("items.yaml").display().to_string()).await?;
+        db::seed::<taken_items::ActiveModel>(db, &base.join("taken_items.yaml").display().to_string()).await?;
+        db::seed::<inventory_entries::ActiveModel>(db, &base.join("inventory_entries.yaml").display().to_string()).await?;
         //db::seed::<notes::ActiveModel>(db, &base.join("notes.yaml").display().to_string()).await?;
+        reset_sequences(db, &["users", "bags", "bag_members", "items", "taken_items", "inventory_entries"]).await?;
         Ok(())
     }
+}
+
+/// Moves the id sequences of `tables` past the ids of the fixtures, which
+/// Postgres does not do for rows inserted with explicit ids.
+async fn reset_sequences(db: &DatabaseConnection, tables: &[&str]) -> Result<()> {
+    if db.get_database_backend() != DbBackend::Postgres {
+        return Ok(());
+    }
+    for table in tables {
+        db.execute_unprepared(&format!(
+            "SELECT setval(pg_get_serial_sequence('{table}', 'id'), (SELECT MAX(id) FROM {table}))"
+        ))
+        .await?;
+    }
+    Ok(())
 }
\ No newline at end of file
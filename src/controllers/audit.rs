@@ -0,0 +1,22 @@
+#![allow(clippy::unused_async)]
+
+use axum::extract::Query;
+use loco_rs::prelude::*;
+use crate::models::{audit_events, users};
+
+/// Lists the changes made in every bag the user runs as a game master or
+/// owner, optionally narrowed down by bag, actor, entity and time range.
+#[axum::debug_handler]
+pub async fn list(State(ctx): State<AppContext>,
+                  auth: auth::JWT,
+                  filter: Option<Query<interface::AuditFilter>>,
+) -> Result<Json<interface::AuditPage>> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(audit_events::Model::list(&ctx.db, user.id, filter.map(|f| f.0)).await?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("audit")
+        .add("/", get(list))
+}
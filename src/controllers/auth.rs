@@ -2,7 +2,6 @@ use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    mailers::auth::AuthMailer,
     models::{
         _entities::users,
         users::{LoginParams, RegisterParams},
@@ -45,7 +44,7 @@ async fn register(
         }
     };
 
-    let user = user
+    user
         .into_active_model()
         .set_email_verification_sent(&ctx.db)
         .await?
@@ -58,8 +57,8 @@ async fn register(
     format::json(())
 }
 
-/// Verify register user. if the user not verified his email, he can't login to
-/// the system.
+// Verify register user. if the user not verified his email, he can't login to
+// the system.
 /*async fn verify(
     State(ctx): State<AppContext>,
     Json(params): Json<VerifyParams>,
@@ -77,10 +76,10 @@ async fn register(
     format::json(())
 }*/
 
-/// In case the user forgot his password  this endpoints generate a forgot token
-/// and send email to the user. In case the email not found in our DB, we are
-/// returning a valid request for for security reasons (not exposing users DB
-/// list).
+// In case the user forgot his password  this endpoints generate a forgot token
+// and send email to the user. In case the email not found in our DB, we are
+// returning a valid request for for security reasons (not exposing users DB
+// list).
 /*async fn forgot(
     State(ctx): State<AppContext>,
     Json(params): Json<ForgotParams>,
@@ -101,7 +100,7 @@ async fn register(
     format::json(())
 }*/
 
-/// reset user password by the given parameters
+// reset user password by the given parameters
 /*async fn reset(State(ctx): State<AppContext>, Json(params): Json<ResetParams>) -> Result<Json<()>> {
     let Ok(user) = users::Model::find_by_reset_token(&ctx.db, &params.token).await else {
         // we don't want to expose our users email. if the email is invalid we still
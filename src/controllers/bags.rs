@@ -0,0 +1,116 @@
+#![allow(clippy::unused_async)]
+
+use axum::http::StatusCode;
+use loco_rs::controller::ErrorDetail;
+use loco_rs::prelude::*;
+use loco_rs::model::ModelError;
+use interface::BagRole;
+use crate::models::users;
+use crate::models::bags;
+use crate::models::bag_members;
+
+/// A bag loaded on behalf of the user in the JWT, with that user's role in it.
+pub struct BagAccess {
+    pub user: users::Model,
+    pub bag: bags::Model,
+    pub role: BagRole,
+}
+
+/// Returns a 403 for members whose role is too low for the action.
+pub fn forbidden<T>() -> Result<T> {
+    Err(Error::CustomError(
+        StatusCode::FORBIDDEN,
+        ErrorDetail::new("forbidden", "Your role in this bag does not allow this action"),
+    ))
+}
+
+/// Loads the bag with the given id on behalf of the user in the JWT, making
+/// sure they hold at least the `required` role in it.
+///
+/// Bags the user is not a member of are reported as not found, so other
+/// users' bag ids are not leaked.
+pub async fn load_bag(ctx: &AppContext, auth: &auth::JWT, bag_id: i32, required: BagRole) -> Result<BagAccess> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let member = match bag_members::Model::find_membership(&ctx.db, bag_id, user.id).await {
+        Ok(member) => member,
+        Err(ModelError::EntityNotFound) => return not_found(),
+        Err(err) => return Err(err.into()),
+    };
+
+    let role = member.role();
+    if role < required {
+        return forbidden();
+    }
+
+    let bag = bags::Model::find_by_id(&ctx.db, bag_id).await?;
+    Ok(BagAccess { user, bag, role })
+}
+
+#[axum::debug_handler]
+pub async fn create(State(ctx): State<AppContext>,
+                    auth: auth::JWT,
+                    Json(create): Json<interface::CreateUpdateBag>
+) -> Result<Json<interface::Bag>> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    let bag = bags::Model::create(
+        &ctx.db,
+        user.id,
+        create
+    ).await?;
+
+    format::json(bag.into())
+}
+
+#[axum::debug_handler]
+pub async fn list(State(ctx): State<AppContext>,
+                  auth: auth::JWT,
+) -> Result<Json<Vec<interface::Bag>>> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    format::json(
+        bags::Model::list_for_user(&ctx.db, user.id)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    )
+}
+
+#[axum::debug_handler]
+async fn read(State(ctx): State<AppContext>,
+              auth: auth::JWT,
+              Path(bag_id): Path<i32>) -> Result<Json<interface::Bag>> {
+    format::json(load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag.into())
+}
+
+#[axum::debug_handler]
+pub async fn update(State(ctx): State<AppContext>,
+                    auth: auth::JWT,
+                    Path(bag_id): Path<i32>,
+                    Json(update): Json<interface::CreateUpdateBag>
+) -> Result<Json<interface::Bag>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Owner).await?;
+
+    format::json(bags::Model::update(&ctx.db, access.bag.id, update).await?.into())
+}
+
+#[axum::debug_handler]
+pub async fn delete_bag(State(ctx): State<AppContext>,
+                        auth: auth::JWT,
+                        Path(bag_id): Path<i32>) -> Result<()> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Owner).await?;
+
+    bags::Model::delete(&ctx.db, access.bag.id).await?;
+    Ok(())
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("bags")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/:bag_id", get(read))
+        .add("/:bag_id", post(update))
+        .add("/:bag_id", delete(delete_bag))
+}
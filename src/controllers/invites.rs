@@ -0,0 +1,22 @@
+#![allow(clippy::unused_async)]
+
+use loco_rs::prelude::*;
+use crate::models::{bag_invites, bags, users};
+
+#[axum::debug_handler]
+pub async fn accept(State(ctx): State<AppContext>,
+                    auth: auth::JWT,
+                    Json(accept): Json<interface::AcceptInvite>
+) -> Result<Json<interface::Bag>> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    let member = bag_invites::Model::accept(&ctx.db, &accept.token, user.id).await?;
+
+    format::json(bags::Model::find_by_id(&ctx.db, member.bag_id).await?.into())
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("invites")
+        .add("/accept", post(accept))
+}
@@ -1,79 +1,298 @@
 #![allow(clippy::unused_async)]
 
-use axum::extract::Query;
+use axum::{body::Body, extract::Query, routing::patch, http::{header, HeaderMap, StatusCode}, response::Response};
+use loco_rs::controller::ErrorDetail;
 use loco_rs::prelude::*;
-use crate::models::users;
-use crate::models::items;
+use crate::item_files;
+use crate::models::{
+    audit_events::Actor,
+    inventory_entries::{self, StockError},
+    items::{self, UpdateError},
+};
+use interface::BagRole;
+use super::bags::load_bag;
 
 #[axum::debug_handler]
 pub async fn create(State(ctx): State<AppContext>,
                     auth: auth::JWT,
+                    Path(bag_id): Path<i32>,
                     Json(create): Json<interface::CreateUpdateItem>
 ) -> Result<Json<interface::Item>> {
-    let _user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
 
     let item = items::Model::create(
         &ctx.db,
+        Actor::User(&access.user),
+        access.bag.id,
         create
     ).await?;
 
-    format::json(item.into())
+    format::json(item.into_item(&ctx.db).await?)
+}
+
+/// Entity tag of the item, which changes with each of its versions.
+fn etag(item: &interface::Item) -> String {
+    format!("\"{}\"", item.version)
+}
+
+/// Reads the versions an `If-Match` header accepts, following RFC 9110. A
+/// missing header and `*` match any version, otherwise the header lists
+/// entity-tags the item must match. Updates compare tags strongly, so weak
+/// tags and tags that were never handed out match no version.
+///
+/// # Errors
+///
+/// When the header is not a valid `If-Match` value
+fn if_match(headers: &HeaderMap) -> Result<Option<Vec<i32>>> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+    let invalid = || Error::CustomError(
+        StatusCode::BAD_REQUEST,
+        ErrorDetail::new("invalid_if_match", "If-Match must be * or a list of entity-tags"),
+    );
+    let value = value.to_str().map_err(|_| invalid())?.trim();
+    if value == "*" {
+        return Ok(None);
+    }
+
+    let mut versions = vec![];
+    let mut rest = value;
+    let mut tags = 0;
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ',']);
+        if rest.is_empty() {
+            break;
+        }
+        let (weak, tag) = rest.strip_prefix("W/").map_or((false, rest), |tag| (true, tag));
+        let (opaque, after) = tag
+            .strip_prefix('"')
+            .and_then(|tag| tag.split_once('"'))
+            .ok_or_else(invalid)?;
+        // tags are separated by commas, with optional whitespace around them
+        let after = after.trim_start_matches([' ', '\t']);
+        if !(after.is_empty() || after.starts_with(',')) {
+            return Err(invalid());
+        }
+        if !weak {
+            if let Ok(version) = opaque.parse() {
+                versions.push(version);
+            }
+        }
+        tags += 1;
+        rest = after;
+    }
+    if tags == 0 {
+        return Err(invalid());
+    }
+    Ok(Some(versions))
+}
+
+/// Returns the item along with its `ETag`.
+fn render_item(item: interface::Item) -> Result<Response> {
+    format::render().header(header::ETAG, etag(&item)).json(item)
 }
 
 #[axum::debug_handler]
 async fn read(State(ctx): State<AppContext>,
                   auth: auth::JWT,
-                  Path(id): Path<i32>) -> Result<Json<interface::Item>> {
-    let _user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+                  Path((bag_id, id)): Path<(i32, i32)>) -> Result<Response> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag;
 
-    format::json(
-        items::Model::find_by_id(&ctx.db, id)
+    render_item(
+        items::Model::find_by_id(&ctx.db, bag.id, id)
+            .await?
+            .into_item(&ctx.db)
             .await?
-            .into()
     )
 }
 
+/// Overwrites the item. With an `If-Match` header the update is refused with
+/// a 412 when the item changed since the client read it.
 #[axum::debug_handler]
 pub async fn update(State(ctx): State<AppContext>,
                 auth: auth::JWT,
-                Path(id): Path<i32>,
+                Path((bag_id, id)): Path<(i32, i32)>,
+                headers: HeaderMap,
                 Json(update): Json<interface::CreateUpdateItem>
-) -> Result<Json<interface::Item>> {
-    let _user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+) -> Result<Response> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
 
-    format::json(items::Model::update(
-                &ctx.db,
-                id,
-                update
-            )
-           .await?
-           .into())
+    let versions = if_match(&headers)?;
+    let updated = items::Model::update(&ctx.db, Actor::User(&access.user), access.bag.id, id, versions.as_deref(), update).await;
+    render_updated(&ctx, id, updated).await
+}
+
+/// Changes only the fields sent, honoring `If-Match` like a full update.
+#[axum::debug_handler]
+pub async fn patch_item(State(ctx): State<AppContext>,
+                        auth: auth::JWT,
+                        Path((bag_id, id)): Path<(i32, i32)>,
+                        headers: HeaderMap,
+                        Json(patch): Json<interface::PatchItem>
+) -> Result<Response> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
+
+    let versions = if_match(&headers)?;
+    let patched = items::Model::patch(&ctx.db, Actor::User(&access.user), access.bag.id, id, versions.as_deref(), patch).await;
+    render_updated(&ctx, id, patched).await
+}
+
+/// Returns the updated item, or a 412 when it changed since the client read it.
+async fn render_updated(ctx: &AppContext, id: i32, updated: std::result::Result<items::Model, UpdateError>) -> Result<Response> {
+    match updated {
+        Ok(item) => render_item(item.into_item(&ctx.db).await?),
+        Err(UpdateError::VersionMismatch(current_version)) => format::render()
+            .status(StatusCode::PRECONDITION_FAILED)
+            .json(interface::VersionMismatch {
+                error: "version_mismatch".to_string(),
+                item_id: id,
+                current_version,
+                description: "The item changed since it was last read".to_string(),
+            }),
+        Err(UpdateError::Model(err)) => Err(err.into()),
+    }
 }
 
 #[axum::debug_handler]
 pub async fn delete_item(State(ctx): State<AppContext>,
                     auth: auth::JWT,
-                    Path(id): Path<i32>) -> Result<()> {
-    let _user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+                    Path((bag_id, id)): Path<(i32, i32)>) -> Result<()> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
 
-    items::Model::delete(&ctx.db, id).await?;
+    items::Model::delete(&ctx.db, Actor::User(&access.user), access.bag.id, id).await?;
     Ok(())
 }
 
+#[axum::debug_handler]
+pub async fn restore_item(State(ctx): State<AppContext>,
+                          auth: auth::JWT,
+                          Path((bag_id, id)): Path<(i32, i32)>) -> Result<Json<interface::Item>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
+
+    format::json(
+        items::Model::restore(&ctx.db, Actor::User(&access.user), access.bag.id, id)
+            .await?
+            .into_item(&ctx.db)
+            .await?
+    )
+}
+
+/// Returns the item with its new stock, or a 422 when the stock cannot be
+/// changed that way.
+async fn render_stock(ctx: &AppContext, changed: std::result::Result<items::Model, StockError>) -> Result<Json<interface::Item>> {
+    match changed {
+        Ok(item) => format::json(item.into_item(&ctx.db).await?),
+        Err(StockError::Model(err)) => Err(err.into()),
+        Err(err) => Err(Error::CustomError(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorDetail::new("invalid_stock_change", &err.to_string()),
+        )),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn restock_item(State(ctx): State<AppContext>,
+                          auth: auth::JWT,
+                          Path((bag_id, id)): Path<(i32, i32)>,
+                          Json(restock): Json<interface::Restock>
+) -> Result<Json<interface::Item>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
+
+    let restocked = inventory_entries::Model::restock(&ctx.db, Actor::User(&access.user), access.bag.id, id, restock).await;
+    render_stock(&ctx, restocked).await
+}
+
+#[axum::debug_handler]
+pub async fn adjust_item(State(ctx): State<AppContext>,
+                         auth: auth::JWT,
+                         Path((bag_id, id)): Path<(i32, i32)>,
+                         Json(adjust): Json<interface::AdjustStock>
+) -> Result<Json<interface::Item>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
+
+    let adjusted = inventory_entries::Model::adjust(&ctx.db, Actor::User(&access.user), access.bag.id, id, adjust).await;
+    render_stock(&ctx, adjusted).await
+}
+
+#[axum::debug_handler]
+pub async fn inventory(State(ctx): State<AppContext>,
+                       auth: auth::JWT,
+                       Path((bag_id, id)): Path<(i32, i32)>) -> Result<Json<Vec<interface::InventoryEntry>>> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag;
+    format::json(inventory_entries::Model::list(&ctx.db, bag.id, id).await?)
+}
+
 #[axum::debug_handler]
 pub async fn list_items(State(ctx): State<AppContext>,
+                        Path(bag_id): Path<i32>,
                         filter: Option<Query<interface::ItemFilter>>,
                         auth: auth::JWT,
 ) -> Result<Json<interface::ItemPage>> {
-    let _user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
-    format::json(items::Model::list(&ctx.db, filter.map(|f| f.0)).await?)
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag;
+    format::json(items::Model::list(&ctx.db, bag.id, filter.map(|f| f.0)).await?)
+}
+
+/// Imports a CSV, JSON or YAML list of items sent as the request body. When
+/// a row is invalid nothing is imported and the report comes back as a 422.
+#[axum::debug_handler]
+pub async fn import_items(State(ctx): State<AppContext>,
+                          auth: auth::JWT,
+                          Path(bag_id): Path<i32>,
+                          options: Option<Query<interface::ItemImportOptions>>,
+                          body: String,
+) -> Result<Response> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
+    let options = options.map(|o| o.0).unwrap_or_default();
+
+    let rows = item_files::parse(options.format, &body).map_err(|err| Error::CustomError(
+        StatusCode::BAD_REQUEST,
+        ErrorDetail::new("invalid_file", &err.to_string()),
+    ))?;
+    let report = items::Model::import(&ctx.db, Actor::User(&access.user), access.bag.id, rows, &options).await?;
+
+    let status = if report.errors.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
+    };
+    format::render().status(status).json(report)
+}
+
+#[axum::debug_handler]
+pub async fn export_items(State(ctx): State<AppContext>,
+                          auth: auth::JWT,
+                          Path(bag_id): Path<i32>,
+                          options: Option<Query<interface::ItemExportOptions>>,
+) -> Result<Response> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag;
+    let format = options.map(|o| o.0).unwrap_or_default().format;
+
+    let content = item_files::write(format, items::Model::export(&ctx.db, bag.id).await?)
+        .map_err(|err| Error::Message(err.to_string()))?;
+    Ok(format::render()
+        .header(header::CONTENT_TYPE, item_files::content_type(format))
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"items.{}\"", item_files::extension(format)),
+        )
+        .response()
+        .body(Body::from(content))?)
 }
 
 pub fn routes() -> Routes {
     Routes::new()
-        .prefix("items")
+        .prefix("bags/:bag_id/items")
+        .add("/", get(list_items))
         .add("/", post(create))
+        .add("/import", post(import_items))
+        .add("/export", get(export_items))
         .add("/:id", get(read))
         .add("/:id", post(update))
+        .add("/:id", patch(patch_item))
         .add("/:id", delete(delete_item))
+        .add("/:id/restore", post(restore_item))
+        .add("/:id/inventory", get(inventory))
+        .add("/:id/restock", post(restock_item))
+        .add("/:id/adjust", post(adjust_item))
 }
@@ -0,0 +1,95 @@
+#![allow(clippy::unused_async)]
+
+use loco_rs::prelude::*;
+use interface::BagRole;
+use crate::models::{bag_invites, bag_members, users};
+use super::bags::{forbidden, load_bag};
+
+#[axum::debug_handler]
+pub async fn list_members(State(ctx): State<AppContext>,
+                          auth: auth::JWT,
+                          Path(bag_id): Path<i32>) -> Result<Json<Vec<interface::BagMember>>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?;
+
+    format::json(bag_members::Model::list_for_bag(&ctx.db, access.bag.id).await?)
+}
+
+#[axum::debug_handler]
+pub async fn update_member(State(ctx): State<AppContext>,
+                           auth: auth::JWT,
+                           Path((bag_id, user_pid)): Path<(i32, String)>,
+                           Json(update): Json<interface::UpdateBagMember>
+) -> Result<Json<()>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Owner).await?;
+    let member = users::Model::find_by_pid(&ctx.db, &user_pid).await?;
+
+    // a bag has exactly one owner, and it keeps that role
+    if update.role == BagRole::Owner || member.id == access.bag.user_id {
+        return forbidden();
+    }
+
+    bag_members::Model::update_role(&ctx.db, access.bag.id, member.id, update.role).await?;
+    format::json(())
+}
+
+#[axum::debug_handler]
+pub async fn remove_member(State(ctx): State<AppContext>,
+                           auth: auth::JWT,
+                           Path((bag_id, user_pid)): Path<(i32, String)>) -> Result<Json<()>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?;
+    let member = users::Model::find_by_pid(&ctx.db, &user_pid).await?;
+
+    // members may leave on their own; only the owner removes others
+    let leaving = member.id == access.user.id;
+    if member.id == access.bag.user_id || (!leaving && access.role != BagRole::Owner) {
+        return forbidden();
+    }
+
+    bag_members::Model::remove(&ctx.db, access.bag.id, member.id).await?;
+    format::json(())
+}
+
+#[axum::debug_handler]
+pub async fn list_invites(State(ctx): State<AppContext>,
+                          auth: auth::JWT,
+                          Path(bag_id): Path<i32>) -> Result<Json<Vec<interface::Invite>>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
+
+    format::json(
+        bag_invites::Model::list_for_bag(&ctx.db, access.bag.id)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    )
+}
+
+#[axum::debug_handler]
+pub async fn create_invite(State(ctx): State<AppContext>,
+                           auth: auth::JWT,
+                           Path(bag_id): Path<i32>,
+                           Json(create): Json<interface::CreateInvite>
+) -> Result<Json<interface::Invite>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
+
+    // nobody can hand out a role above their own, and ownership is never shared
+    if create.role == BagRole::Owner || create.role > access.role {
+        return forbidden();
+    }
+
+    format::json(
+        bag_invites::Model::create(&ctx.db, access.bag.id, access.user.id, create.role)
+            .await?
+            .into()
+    )
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("bags/:bag_id")
+        .add("/members", get(list_members))
+        .add("/members/:user_pid", post(update_member))
+        .add("/members/:user_pid", delete(remove_member))
+        .add("/invites", get(list_invites))
+        .add("/invites", post(create_invite))
+}
@@ -1,5 +1,11 @@
 pub mod auth;
 pub mod user;
 
+pub mod audit;
+pub mod bags;
+pub mod invites;
 pub mod items;
+pub mod members;
+pub mod rounds;
+pub mod tags;
 pub mod taken;
\ No newline at end of file
@@ -0,0 +1,63 @@
+#![allow(clippy::unused_async)]
+
+use axum::http::StatusCode;
+use loco_rs::controller::ErrorDetail;
+use loco_rs::prelude::*;
+use interface::BagRole;
+use crate::models::audit_events::Actor;
+use crate::models::game_rounds::{self, AdvanceError};
+use crate::settings::Settings;
+use super::bags::load_bag;
+
+pub async fn current(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path(bag_id): Path<i32>,
+) -> Result<Json<interface::GameRound>> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag;
+    format::json(game_rounds::Model::current(&ctx.db, bag.id).await?)
+}
+
+/// Advances the game to its next round, or returns a 409 when someone else
+/// advanced it at the same time.
+pub async fn advance(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path(bag_id): Path<i32>,
+) -> Result<Json<interface::RoundSummary>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
+    match game_rounds::Model::advance(&ctx.db, Actor::User(&access.user), access.bag.id, Settings::from_context(&ctx).history_depth).await {
+        Ok(summary) => format::json(summary),
+        Err(AdvanceError::Concurrent(_)) => Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("round_advanced", "The round was advanced by someone else at the same time"),
+        )),
+        Err(AdvanceError::Model(err)) => Err(err.into()),
+    }
+}
+
+/// Starts a new game in the bag, or returns a 409 when someone else started
+/// one at the same time.
+pub async fn new_game(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path(bag_id): Path<i32>,
+) -> Result<Json<interface::GameRound>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?;
+    match game_rounds::Model::new_game(&ctx.db, Actor::User(&access.user), access.bag.id).await {
+        Ok(round) => format::json(round),
+        Err(AdvanceError::Concurrent(_)) => Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("game_started", "A new game was started by someone else at the same time"),
+        )),
+        Err(AdvanceError::Model(err)) => Err(err.into()),
+    }
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("bags/:bag_id/rounds")
+        .add("/", get(current))
+        .add("/advance", post(advance))
+        .add("/new-game", post(new_game))
+}
@@ -0,0 +1,72 @@
+#![allow(clippy::unused_async)]
+
+use loco_rs::prelude::*;
+use crate::models::tags;
+use interface::BagRole;
+use super::bags::load_bag;
+
+#[axum::debug_handler]
+pub async fn list_tags(State(ctx): State<AppContext>,
+                       auth: auth::JWT,
+                       Path(bag_id): Path<i32>) -> Result<Json<Vec<interface::Tag>>> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag;
+
+    format::json(
+        tags::Model::list_for_bag(&ctx.db, bag.id)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<interface::Tag>>()
+    )
+}
+
+#[axum::debug_handler]
+pub async fn create(State(ctx): State<AppContext>,
+                    auth: auth::JWT,
+                    Path(bag_id): Path<i32>,
+                    Json(create): Json<interface::CreateUpdateTag>
+) -> Result<Json<interface::Tag>> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?.bag;
+
+    format::json(tags::Model::create(&ctx.db, bag.id, create).await?.into())
+}
+
+#[axum::debug_handler]
+async fn read(State(ctx): State<AppContext>,
+              auth: auth::JWT,
+              Path((bag_id, id)): Path<(i32, i32)>) -> Result<Json<interface::Tag>> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag;
+
+    format::json(tags::Model::find_by_id(&ctx.db, bag.id, id).await?.into())
+}
+
+#[axum::debug_handler]
+pub async fn update(State(ctx): State<AppContext>,
+                    auth: auth::JWT,
+                    Path((bag_id, id)): Path<(i32, i32)>,
+                    Json(update): Json<interface::CreateUpdateTag>
+) -> Result<Json<interface::Tag>> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?.bag;
+
+    format::json(tags::Model::update(&ctx.db, bag.id, id, update).await?.into())
+}
+
+#[axum::debug_handler]
+pub async fn delete_tag(State(ctx): State<AppContext>,
+                        auth: auth::JWT,
+                        Path((bag_id, id)): Path<(i32, i32)>) -> Result<()> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::GameMaster).await?.bag;
+
+    tags::Model::delete(&ctx.db, bag.id, id).await?;
+    Ok(())
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("bags/:bag_id/tags")
+        .add("/", get(list_tags))
+        .add("/", post(create))
+        .add("/:id", get(read))
+        .add("/:id", post(update))
+        .add("/:id", delete(delete_tag))
+}
@@ -2,52 +2,231 @@
 
 use axum::debug_handler;
 use loco_rs::prelude::*;
-use crate::models::taken_items;
-use crate::models::users;
+use axum::{extract::Query, http::StatusCode, response::Response};
+use loco_rs::controller::ErrorDetail;
+use crate::models::audit_events::Actor;
+use crate::models::draw_transitions::{self, HistoryError};
+use crate::models::taken_items::{self, DrawError, RoundsError};
+use interface::BagRole;
+use crate::draw;
+use crate::settings::Settings;
+use super::bags::load_bag;
 
 #[debug_handler]
-pub async fn get_current(
+pub async fn get_hand(
     State(ctx): State<AppContext>,
     auth: auth::JWT,
-) -> Result<Json<Option<interface::TakenItem>>> {
-    let _user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
-    format::json(taken_items::Model::get_current(&ctx.db).await?)
+    Path(bag_id): Path<i32>,
+) -> Result<Json<Vec<interface::TakenItem>>> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag;
+    format::json(taken_items::Model::get_hand(&ctx.db, bag.id).await?)
 }
 
+/// Decrements the newest draw of the hand, for clients holding a single draw.
 pub async fn decrement_rounds(
     State(ctx): State<AppContext>,
     auth: auth::JWT,
+    Path(bag_id): Path<i32>,
 ) -> Result<Json<Option<interface::TakenItem>>> {
-    let _user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
-    format::json(taken_items::Model::decrement_rounds(&ctx.db).await?)
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    format::json(taken_items::Model::decrement_rounds(&ctx.db, Actor::User(&access.user), access.bag.id, Settings::from_context(&ctx).history_depth).await?)
 }
 
+/// Finishes the newest draw of the hand, for clients holding a single draw.
 pub async fn mark_done(
     State(ctx): State<AppContext>,
     auth: auth::JWT,
+    Path(bag_id): Path<i32>,
 ) -> Result<()> {
-    let _user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
 
-    taken_items::Model::mark_done(&ctx.db).await?;
+    taken_items::Model::mark_done(&ctx.db, Actor::User(&access.user), access.bag.id, Settings::from_context(&ctx).history_depth).await?;
     Ok(())
 }
 
-pub async fn get_random(
+pub async fn decrement_draw(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path((bag_id, id)): Path<(i32, i32)>,
+) -> Result<Json<interface::TakenItem>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    format::json(taken_items::Model::decrement_draw(&ctx.db, Actor::User(&access.user), access.bag.id, id, Settings::from_context(&ctx).history_depth).await?)
+}
+
+pub async fn mark_draw_done(
     State(ctx): State<AppContext>,
     auth: auth::JWT,
+    Path((bag_id, id)): Path<(i32, i32)>,
 ) -> Result<Json<interface::TakenItem>> {
-    let _user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    format::json(taken_items::Model::mark_draw_done(&ctx.db, Actor::User(&access.user), access.bag.id, id, Settings::from_context(&ctx).history_depth).await?)
+}
+
+/// Adds rounds to a draw, or takes them off with a negative delta.
+pub async fn adjust_rounds(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path((bag_id, id)): Path<(i32, i32)>,
+    Json(adjust): Json<interface::AdjustRounds>,
+) -> Result<Json<interface::TakenItem>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    match taken_items::Model::adjust_rounds(&ctx.db, Actor::User(&access.user), access.bag.id, id, adjust, Settings::from_context(&ctx).history_depth).await {
+        Ok(taken) => format::json(taken),
+        Err(RoundsError::Model(err)) => Err(err.into()),
+        Err(err) => Err(Error::CustomError(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorDetail::new("invalid_rounds_change", &err.to_string()),
+        )),
+    }
+}
+
+pub async fn pause(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path((bag_id, id)): Path<(i32, i32)>,
+) -> Result<Json<interface::TakenItem>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    format::json(taken_items::Model::pause(&ctx.db, Actor::User(&access.user), access.bag.id, id, Settings::from_context(&ctx).history_depth).await?)
+}
+
+pub async fn resume(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path((bag_id, id)): Path<(i32, i32)>,
+) -> Result<Json<interface::TakenItem>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    format::json(taken_items::Model::resume(&ctx.db, Actor::User(&access.user), access.bag.id, id, Settings::from_context(&ctx).history_depth).await?)
+}
+
+/// Turns errors of stepping through the draw history into 409s, naming what
+/// was asked for when there is nothing left to step over.
+fn history_error(err: HistoryError, empty: &str) -> Error {
+    match err {
+        HistoryError::Empty(_) => Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new(&format!("nothing_to_{empty}"), &format!("There is no change to the draws left to {empty}")),
+        ),
+        HistoryError::Conflict(_) => Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("draws_changed", "The draws were changed by something else since"),
+        ),
+        HistoryError::RoundAdvanced(_) => Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("round_advanced", "The round was advanced since, which cannot be undone"),
+        ),
+        HistoryError::Model(err) => err.into(),
+    }
+}
 
-    format::json(
-        taken_items::Model::get_random(&ctx.db).await?
-    )
+/// Reverts the last change to the bag's draws, like a decrement or done.
+pub async fn undo(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path(bag_id): Path<i32>,
+) -> Result<Json<interface::DrawTransition>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    draw_transitions::Model::undo(&ctx.db, Actor::User(&access.user), access.bag.id)
+        .await
+        .map_err(|err| history_error(err, "undo"))
+        .and_then(format::json)
+}
+
+/// Applies the last undone change of the bag's draws again.
+pub async fn redo(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path(bag_id): Path<i32>,
+) -> Result<Json<interface::DrawTransition>> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    draw_transitions::Model::redo(&ctx.db, Actor::User(&access.user), access.bag.id)
+        .await
+        .map_err(|err| history_error(err, "redo"))
+        .and_then(format::json)
+}
+
+pub async fn get_random(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path(bag_id): Path<i32>,
+    tags: Option<Query<interface::TagFilter>>,
+) -> Result<Response> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    let bag = &access.bag;
+    // bags without their own strategy use the one from the config
+    let settings = Settings::from_context(&ctx);
+    let kind = bag.draw_strategy().unwrap_or(settings.draw_strategy);
+    let hand_size = bag.hand_size.unwrap_or(settings.hand_size);
+
+    let tags = tags.map(|t| t.0).unwrap_or_default();
+
+    render_draw(taken_items::Model::get_random(&ctx.db, bag, Actor::User(&access.user), draw::strategy_for(kind).as_ref(), &settings.rounds, hand_size, &tags).await)
+}
+
+/// Puts the item of a draw back into the bag and draws another one in its
+/// place, or returns a 409 once the bag's reroll budget is spent.
+pub async fn reroll(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path((bag_id, id)): Path<(i32, i32)>,
+    tags: Option<Query<interface::TagFilter>>,
+) -> Result<Response> {
+    let access = load_bag(&ctx, &auth, bag_id, BagRole::Player).await?;
+    let bag = &access.bag;
+    let settings = Settings::from_context(&ctx);
+    let kind = bag.draw_strategy().unwrap_or(settings.draw_strategy);
+    let budget = bag.reroll_budget.or(settings.reroll_budget);
+
+    let tags = tags.map(|t| t.0).unwrap_or_default();
+
+    render_draw(taken_items::Model::reroll(&ctx.db, bag, Actor::User(&access.user), id, draw::strategy_for(kind).as_ref(), &settings.rounds, budget, &tags).await)
+}
+
+fn render_draw(drawn: std::result::Result<interface::TakenItem, DrawError>) -> Result<Response> {
+    match drawn {
+        Ok(taken) => format::render().json(taken),
+        Err(DrawError::BagExhausted(bag_id)) => format::render()
+            .status(StatusCode::CONFLICT)
+            .json(interface::BagExhausted {
+                error: "bag_exhausted".to_string(),
+                bag_id,
+                description: "Every item in the bag has been drawn".to_string(),
+            }),
+        Err(err @ DrawError::RerollBudgetSpent(_)) => Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("reroll_budget_spent", &err.to_string()),
+        )),
+        Err(DrawError::Concurrent(_)) => Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("concurrent_draw", "The hand was drawn into by someone else at the same time"),
+        )),
+        Err(DrawError::Model(err)) => Err(err.into()),
+    }
+}
+
+pub async fn history(
+    State(ctx): State<AppContext>,
+    auth: auth::JWT,
+    Path(bag_id): Path<i32>,
+    filter: Option<Query<interface::TakenItemHistoryFilter>>,
+) -> Result<Json<interface::TakenItemHistory>> {
+    let bag = load_bag(&ctx, &auth, bag_id, BagRole::Viewer).await?.bag;
+    format::json(taken_items::Model::history(&ctx.db, bag.id, filter.map(|f| f.0)).await?)
 }
 
 pub fn routes() -> Routes {
     Routes::new()
-        .prefix("taken")
-        .add("/", get(get_current))
+        .prefix("bags/:bag_id/taken")
+        .add("/", get(get_hand))
         .add("/", post(get_random))
         .add("/decrement", post(decrement_rounds))
         .add("/done", post(mark_done))
+        .add("/undo", post(undo))
+        .add("/redo", post(redo))
+        .add("/history", get(history))
+        .add("/:id/decrement", post(decrement_draw))
+        .add("/:id/done", post(mark_draw_done))
+        .add("/:id/rounds", post(adjust_rounds))
+        .add("/:id/pause", post(pause))
+        .add("/:id/resume", post(resume))
+        .add("/:id/reroll", post(reroll))
 }
@@ -0,0 +1,297 @@
+//! Dice notation for how many rounds a drawn item lasts, like `1d4+1` or
+//! `2d6 keep highest`.
+//!
+//! An expression adds or subtracts terms that are either a number or `NdS`,
+//! `N` dice of `S` sides each (`N` is 1 when left out). Dice can keep only
+//! their highest or lowest rolls with `kh` / `keep highest` or `kl` /
+//! `keep lowest`, followed by how many rolls to keep, 1 when left out. Case
+//! and spaces between terms do not matter:
+//! ```text
+//! 1d4+1
+//! 2d6 keep highest
+//! 4d6kh3 - 2
+//! d8 + d4
+//! ```
+use std::{fmt, str::FromStr};
+
+use loco_rs::validator::ValidationError;
+use rand::{Rng, RngCore};
+
+/// Most dice a single term can roll.
+pub const MAX_DICE: u32 = 100;
+/// Most sides a die can have.
+pub const MAX_SIDES: u32 = 1000;
+/// Largest number a term can add.
+pub const MAX_CONSTANT: u32 = 1000;
+/// Most terms an expression can have.
+pub const MAX_TERMS: usize = 20;
+
+/// Why some text is not a dice expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiceError {
+    /// the text has no terms at all
+    Empty,
+    /// something that is not a term, sign or keep rule, at the given byte
+    Unexpected(usize),
+    /// a `d` without a number of sides after it
+    MissingSides,
+    TooManyDice(u32),
+    TooManySides(u32),
+    ConstantTooLarge(u32),
+    TooManyTerms,
+    /// keeping none of the dice, or more dice than are rolled
+    InvalidKeep { keep: u32, count: u32 },
+}
+
+impl fmt::Display for DiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the dice expression is empty"),
+            Self::Unexpected(position) => write!(f, "unexpected character at position {position}"),
+            Self::MissingSides => write!(f, "dice need a number of sides, like d6"),
+            Self::TooManyDice(count) => write!(f, "cannot roll {count} dice, between 1 and {MAX_DICE} are allowed"),
+            Self::TooManySides(sides) => write!(f, "dice cannot have {sides} sides, between 1 and {MAX_SIDES} are allowed"),
+            Self::ConstantTooLarge(value) => write!(f, "{value} is more than the largest number allowed, {MAX_CONSTANT}"),
+            Self::TooManyTerms => write!(f, "a dice expression can have at most {MAX_TERMS} terms"),
+            Self::InvalidKeep { keep, count } => write!(f, "cannot keep {keep} of {count} dice"),
+        }
+    }
+}
+
+impl std::error::Error for DiceError {}
+
+/// Which rolls of a group of dice count towards the total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    Highest(u32),
+    Lowest(u32),
+}
+
+/// A single term of an expression, before its sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    Dice { count: u32, sides: u32, keep: Option<Keep> },
+    Constant(u32),
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dice { count, sides, keep } => {
+                write!(f, "{count}d{sides}")?;
+                match keep {
+                    Some(Keep::Highest(keep)) => write!(f, "kh{keep}"),
+                    Some(Keep::Lowest(keep)) => write!(f, "kl{keep}"),
+                    None => Ok(()),
+                }
+            }
+            Self::Constant(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// A parsed dice expression, written back in its short form, like `2d6kh1+1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceExpression {
+    /// every term with whether it is subtracted
+    terms: Vec<(bool, Term)>,
+}
+
+impl fmt::Display for DiceExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (negative, term)) in self.terms.iter().enumerate() {
+            if *negative {
+                write!(f, "-")?;
+            } else if i > 0 {
+                write!(f, "+")?;
+            }
+            write!(f, "{term}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads an expression from the front, skipping spaces between tokens.
+struct Parser<'a> {
+    input: &'a str,
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    fn position(&self) -> usize {
+        self.input.len() - self.rest.len()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        self.rest = self.rest.trim_start();
+        let len = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        if len == 0 {
+            return None;
+        }
+        let (digits, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        // too many digits is too large either way
+        Some(digits.parse().unwrap_or(u32::MAX))
+    }
+
+    fn keep(&mut self) -> Result<Option<Keep>, DiceError> {
+        let keep: fn(u32) -> Keep = if self.eat("keep") {
+            if self.eat("highest") {
+                Keep::Highest
+            } else if self.eat("lowest") {
+                Keep::Lowest
+            } else {
+                return Err(DiceError::Unexpected(self.position()));
+            }
+        } else if self.eat("kl") {
+            Keep::Lowest
+        } else if self.eat("kh") || self.eat("k") {
+            Keep::Highest
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(keep(self.number().unwrap_or(1))))
+    }
+
+    fn term(&mut self) -> Result<Term, DiceError> {
+        let number = self.number();
+        if !self.eat("d") {
+            return match number {
+                Some(value) if value > MAX_CONSTANT => Err(DiceError::ConstantTooLarge(value)),
+                Some(value) => Ok(Term::Constant(value)),
+                None if self.rest.is_empty() => Err(DiceError::Empty),
+                None => Err(DiceError::Unexpected(self.position())),
+            };
+        }
+
+        let count = number.unwrap_or(1);
+        let sides = self.number().ok_or(DiceError::MissingSides)?;
+        if !(1..=MAX_DICE).contains(&count) {
+            return Err(DiceError::TooManyDice(count));
+        }
+        if !(1..=MAX_SIDES).contains(&sides) {
+            return Err(DiceError::TooManySides(sides));
+        }
+        let keep = self.keep()?;
+        if let Some(Keep::Highest(keep) | Keep::Lowest(keep)) = keep {
+            if keep == 0 || keep > count {
+                return Err(DiceError::InvalidKeep { keep, count });
+            }
+        }
+        Ok(Term::Dice { count, sides, keep })
+    }
+}
+
+impl FromStr for DiceExpression {
+    type Err = DiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.to_ascii_lowercase();
+        let mut parser = Parser {
+            input: &input,
+            rest: &input,
+        };
+
+        let mut terms = vec![];
+        let mut negative = parser.eat("-");
+        if !negative {
+            parser.eat("+");
+        }
+        loop {
+            terms.push((negative, parser.term()?));
+            if terms.len() > MAX_TERMS {
+                return Err(DiceError::TooManyTerms);
+            }
+            if parser.eat("+") {
+                negative = false;
+            } else if parser.eat("-") {
+                negative = true;
+            } else if parser.rest.trim_start().is_empty() {
+                return Ok(Self { terms });
+            } else {
+                return Err(DiceError::Unexpected(parser.position()));
+            }
+        }
+    }
+}
+
+/// The values of the rolls that count, in the order they were rolled.
+fn kept_rolls(rolls: &[u32], keep: Option<Keep>) -> Vec<u32> {
+    let (count, highest) = match keep {
+        None => return rolls.to_vec(),
+        Some(Keep::Highest(count)) => (count, true),
+        Some(Keep::Lowest(count)) => (count, false),
+    };
+    let mut order: Vec<usize> = (0..rolls.len()).collect();
+    order.sort_by_key(|&i| rolls[i]);
+    if highest {
+        order.reverse();
+    }
+    let mut kept: Vec<usize> = order.into_iter().take(count as usize).collect();
+    kept.sort_unstable();
+    kept.into_iter().map(|i| rolls[i]).collect()
+}
+
+impl DiceExpression {
+    /// Rolls every die of the expression and adds up the kept rolls and
+    /// numbers.
+    pub fn roll(&self, rng: &mut dyn RngCore) -> interface::DiceRoll {
+        let mut dice = vec![];
+        let mut total = 0_i64;
+        for (negative, term) in &self.terms {
+            let value = match term {
+                Term::Constant(value) => i64::from(*value),
+                Term::Dice { count, sides, keep } => {
+                    let rolls: Vec<u32> = (0..*count).map(|_| rng.gen_range(1..=*sides)).collect();
+                    let kept = kept_rolls(&rolls, *keep);
+                    let value = kept.iter().copied().map(i64::from).sum();
+                    dice.push(interface::DiceRolls {
+                        dice: if *negative { format!("-{term}") } else { term.to_string() },
+                        rolls,
+                        kept,
+                    });
+                    value
+                }
+            };
+            total += if *negative { -value } else { value };
+        }
+        interface::DiceRoll {
+            expression: self.to_string(),
+            dice,
+            total: i32::try_from(total).unwrap_or(if total < 0 { i32::MIN } else { i32::MAX }),
+        }
+    }
+}
+
+/// How many rounds a roll makes a draw last; every draw lasts at least a round.
+#[must_use]
+pub fn rounds(roll: &interface::DiceRoll) -> i16 {
+    i16::try_from(roll.total.max(1)).unwrap_or(i16::MAX)
+}
+
+/// Checks that a stored dice expression can be parsed.
+///
+/// # Errors
+///
+/// When the expression is not valid dice notation
+pub fn validate_expression(expression: &str) -> Result<(), ValidationError> {
+    expression.parse::<DiceExpression>().map(|_| ()).map_err(|err| {
+        let mut error = ValidationError::new("dice");
+        error.message = Some(err.to_string().into());
+        error
+    })
+}
@@ -0,0 +1,122 @@
+//! Strategies for picking which item comes out of a bag.
+//!
+//! A strategy only sees the items that are still available (see
+//! `taken_items::Model::get_random`) and decides which one of them is drawn.
+use interface::{ItemSize, RoundRange};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng, RngCore};
+use sea_orm::FromQueryResult;
+use serde::Deserialize;
+
+/// An item that can currently be drawn from a bag.
+#[derive(Debug, Clone, PartialEq, Eq, FromQueryResult)]
+pub struct Candidate {
+    pub item_id: i32,
+    pub weight: i32,
+    /// how many times the item has been drawn from the bag so far
+    pub times_drawn: i64,
+}
+
+pub trait DrawStrategy: Send + Sync {
+    /// Returns the index of the chosen candidate, or `None` when there is
+    /// nothing to choose from.
+    fn choose(&self, candidates: &[Candidate], rng: &mut dyn RngCore) -> Option<usize>;
+}
+
+/// Every available item is equally likely, regardless of its weight.
+pub struct Uniform;
+
+impl DrawStrategy for Uniform {
+    fn choose(&self, candidates: &[Candidate], rng: &mut dyn RngCore) -> Option<usize> {
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(rng.gen_range(0..candidates.len()))
+    }
+}
+
+/// Items are picked with probability `weight / sum(weights)`.
+pub struct Weighted;
+
+impl DrawStrategy for Weighted {
+    fn choose(&self, candidates: &[Candidate], rng: &mut dyn RngCore) -> Option<usize> {
+        let weights = WeightedIndex::new(candidates.iter().map(|c| c.weight.max(0))).ok()?;
+        Some(weights.sample(rng))
+    }
+}
+
+/// The bag holds `weight` tokens for every item and tokens are drawn without
+/// replacement; once every token is out the bag is refilled. Over a full
+/// cycle each item comes out exactly `weight` times.
+///
+/// The cycle is derived from the draw counts, so no extra state is stored.
+pub struct ShuffleBag;
+
+impl ShuffleBag {
+    /// Tokens each candidate still has in the current cycle.
+    #[must_use]
+    pub fn remaining_tokens(candidates: &[Candidate]) -> Vec<i64> {
+        let cycle = candidates
+            .iter()
+            .filter(|c| c.weight > 0)
+            .map(|c| c.times_drawn / i64::from(c.weight))
+            .min()
+            .unwrap_or(0);
+
+        candidates
+            .iter()
+            .map(|c| (i64::from(c.weight) * (cycle + 1) - c.times_drawn).max(0))
+            .collect()
+    }
+}
+
+impl DrawStrategy for ShuffleBag {
+    fn choose(&self, candidates: &[Candidate], rng: &mut dyn RngCore) -> Option<usize> {
+        let weights = WeightedIndex::new(Self::remaining_tokens(candidates)).ok()?;
+        Some(weights.sample(rng))
+    }
+}
+
+/// Builds the strategy for the given kind.
+#[must_use]
+pub fn strategy_for(kind: interface::DrawStrategyKind) -> Box<dyn DrawStrategy> {
+    match kind {
+        interface::DrawStrategyKind::Uniform => Box::new(Uniform),
+        interface::DrawStrategyKind::Weighted => Box::new(Weighted),
+        interface::DrawStrategyKind::ShuffleBag => Box::new(ShuffleBag),
+    }
+}
+
+/// How many rounds drawn items last when they do not set their own, per size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct RoundsBySize {
+    pub small: RoundRange,
+    pub medium: RoundRange,
+    pub large: RoundRange,
+}
+
+impl Default for RoundsBySize {
+    fn default() -> Self {
+        let range = RoundRange { min: 1, max: 6 };
+        Self {
+            small: range,
+            medium: range,
+            large: range,
+        }
+    }
+}
+
+impl RoundsBySize {
+    #[must_use]
+    pub const fn for_size(&self, size: &ItemSize) -> RoundRange {
+        match size {
+            ItemSize::Small => self.small,
+            ItemSize::Medium => self.medium,
+            ItemSize::Large => self.large,
+        }
+    }
+}
+
+/// Rolls how many rounds a drawn item lasts, uniformly within `range`.
+pub fn roll_rounds(range: RoundRange, rng: &mut dyn RngCore) -> i16 {
+    rng.gen_range(range.min..=range.max.max(range.min))
+}
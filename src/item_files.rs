@@ -0,0 +1,185 @@
+//! Reading and writing lists of items as CSV, JSON or YAML files.
+//!
+//! Every format holds the fields of `interface::CreateUpdateItem`. JSON and
+//! YAML files are a list of items, CSV files have one item per row with a
+//! header row, the tags of an item joined by commas and its rounds in
+//! `min_rounds`, `max_rounds` and `rounds_dice` columns.
+use std::fmt;
+
+use interface::{CreateUpdateItem, ItemFileFormat, ItemSize, RoundRange};
+use serde::{Deserialize, Serialize};
+
+/// A file that cannot be read as a list of items at all.
+#[derive(Debug)]
+pub enum ItemFileError {
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    Yaml(serde_yaml::Error),
+}
+
+impl fmt::Display for ItemFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csv(err) => write!(f, "invalid CSV: {err}"),
+            Self::Json(err) => write!(f, "invalid JSON: {err}"),
+            Self::Yaml(err) => write!(f, "invalid YAML: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ItemFileError {}
+
+/// One row of a parsed file, or why it could not be read as an item.
+pub type ItemRow = Result<CreateUpdateItem, String>;
+
+/// A row of a CSV file, which cannot hold a list of tags directly.
+#[derive(Serialize, Deserialize)]
+struct CsvItem {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    quantity: i32,
+    size: ItemSize,
+    infinite: bool,
+    #[serde(default)]
+    weight: Option<i32>,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    min_rounds: Option<i16>,
+    #[serde(default)]
+    max_rounds: Option<i16>,
+    #[serde(default)]
+    rounds_dice: Option<String>,
+}
+
+impl From<CsvItem> for CreateUpdateItem {
+    fn from(value: CsvItem) -> Self {
+        Self {
+            name: value.name,
+            description: value.description.filter(|description| !description.is_empty()),
+            quantity: value.quantity,
+            size: value.size,
+            infinite: value.infinite,
+            weight: value.weight.unwrap_or(1),
+            tags: value
+                .tags
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(ToString::to_string)
+                .collect(),
+            rounds: match (value.min_rounds, value.max_rounds) {
+                (None, None) => None,
+                // a single bound makes a fixed duration
+                (min, max) => min.or(max).map(|bound| RoundRange {
+                    min: min.unwrap_or(bound),
+                    max: max.unwrap_or(bound),
+                }),
+            },
+            rounds_dice: value.rounds_dice.filter(|dice| !dice.is_empty()),
+        }
+    }
+}
+
+impl From<CreateUpdateItem> for CsvItem {
+    fn from(value: CreateUpdateItem) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            quantity: value.quantity,
+            size: value.size,
+            infinite: value.infinite,
+            weight: Some(value.weight),
+            tags: value.tags.join(","),
+            min_rounds: value.rounds.map(|rounds| rounds.min),
+            max_rounds: value.rounds.map(|rounds| rounds.max),
+            rounds_dice: value.rounds_dice,
+        }
+    }
+}
+
+/// Picks a format from its name or a file extension, ignoring case.
+#[must_use]
+pub fn format_from_name(name: &str) -> Option<ItemFileFormat> {
+    match name.to_lowercase().as_str() {
+        "csv" => Some(ItemFileFormat::Csv),
+        "json" => Some(ItemFileFormat::Json),
+        "yaml" | "yml" => Some(ItemFileFormat::Yaml),
+        _ => None,
+    }
+}
+
+/// Content type of files in `format`.
+#[must_use]
+pub const fn content_type(format: ItemFileFormat) -> &'static str {
+    match format {
+        ItemFileFormat::Csv => "text/csv",
+        ItemFileFormat::Json => "application/json",
+        ItemFileFormat::Yaml => "application/yaml",
+    }
+}
+
+/// File extension of files in `format`.
+#[must_use]
+pub const fn extension(format: ItemFileFormat) -> &'static str {
+    match format {
+        ItemFileFormat::Csv => "csv",
+        ItemFileFormat::Json => "json",
+        ItemFileFormat::Yaml => "yaml",
+    }
+}
+
+/// Reads the items in `content`. Rows that are not valid items are returned
+/// as errors so they can be reported one by one.
+///
+/// # Errors
+///
+/// When the content is not a list in the given format
+pub fn parse(format: ItemFileFormat, content: &str) -> Result<Vec<ItemRow>, ItemFileError> {
+    match format {
+        ItemFileFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(content.as_bytes());
+            // a broken header makes every row unreadable
+            reader.headers().map_err(ItemFileError::Csv)?;
+            Ok(reader
+                .deserialize::<CsvItem>()
+                .map(|row| row.map(Into::into).map_err(|err| err.to_string()))
+                .collect())
+        }
+        ItemFileFormat::Json => Ok(serde_json::from_str::<Vec<serde_json::Value>>(content)
+            .map_err(ItemFileError::Json)?
+            .into_iter()
+            .map(|row| serde_json::from_value(row).map_err(|err| err.to_string()))
+            .collect()),
+        ItemFileFormat::Yaml => Ok(serde_yaml::from_str::<Vec<serde_yaml::Value>>(content)
+            .map_err(ItemFileError::Yaml)?
+            .into_iter()
+            .map(|row| serde_yaml::from_value(row).map_err(|err| err.to_string()))
+            .collect()),
+    }
+}
+
+/// Writes `items` in the given format.
+///
+/// # Errors
+///
+/// When the items cannot be serialized
+pub fn write(format: ItemFileFormat, items: Vec<CreateUpdateItem>) -> Result<String, ItemFileError> {
+    match format {
+        ItemFileFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for item in items {
+                writer.serialize(CsvItem::from(item)).map_err(ItemFileError::Csv)?;
+            }
+            let bytes = writer
+                .into_inner()
+                .map_err(|err| ItemFileError::Csv(err.into_error().into()))?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        ItemFileFormat::Json => serde_json::to_string_pretty(&items).map_err(ItemFileError::Json),
+        ItemFileFormat::Yaml => serde_yaml::to_string(&items).map_err(ItemFileError::Yaml),
+    }
+}
@@ -1,7 +1,11 @@
 pub mod app;
 pub mod controllers;
+pub mod dice;
+pub mod draw;
+pub mod item_files;
 pub mod mailers;
 pub mod models;
+pub mod settings;
 pub mod tasks;
 pub mod views;
 pub mod workers;
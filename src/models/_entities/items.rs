@@ -16,14 +16,56 @@ pub struct Model {
     pub quantity: i32,
     pub size: i16,
     pub infinite: bool,
+    pub bag_id: i32,
+    pub weight: i32,
+    pub search_name: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub search_description: Option<String>,
+    pub deleted_at: Option<DateTime>,
+    pub version: i32,
+    pub min_rounds: Option<i16>,
+    pub max_rounds: Option<i16>,
+    pub rounds_dice: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::bags::Entity",
+        from = "Column::BagId",
+        to = "super::bags::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Bags,
+    #[sea_orm(has_many = "super::item_tags::Entity")]
+    ItemTags,
     #[sea_orm(has_many = "super::taken_items::Entity")]
     TakenItems,
 }
 
+impl Related<super::bags::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bags.def()
+    }
+}
+
+impl Related<super::item_tags::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ItemTags.def()
+    }
+}
+
+impl Related<super::tags::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::item_tags::Relation::Tags.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::item_tags::Relation::Items.def().rev())
+    }
+}
+
 impl Related<super::taken_items::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::TakenItems.def()
@@ -2,6 +2,15 @@
 
 pub mod prelude;
 
+pub mod audit_events;
+pub mod bag_invites;
+pub mod bag_members;
+pub mod bags;
+pub mod draw_transitions;
+pub mod game_rounds;
+pub mod inventory_entries;
+pub mod item_tags;
 pub mod items;
+pub mod tags;
 pub mod taken_items;
 pub mod users;
@@ -1,5 +1,9 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.6
 
 pub use super::{
-    items::Entity as Items, taken_items::Entity as TakenItems, users::Entity as Users,
+    audit_events::Entity as AuditEvents, bag_invites::Entity as BagInvites,
+    bag_members::Entity as BagMembers, bags::Entity as Bags, draw_transitions::Entity as DrawTransitions,
+    game_rounds::Entity as GameRounds, inventory_entries::Entity as InventoryEntries, item_tags::Entity as ItemTags,
+    items::Entity as Items, tags::Entity as Tags, taken_items::Entity as TakenItems,
+    users::Entity as Users,
 };
@@ -14,10 +14,26 @@ pub struct Model {
     pub rounds_left: i16,
     pub done: bool,
     pub rounds_total: i16,
+    pub bag_id: i32,
+    pub restocked: bool,
+    pub drawn_by: Option<i32>,
+    pub rounds_roll: Option<Json>,
+    pub hand_slot: Option<i16>,
+    pub paused: bool,
+    pub rerolled: bool,
+    pub rerolled_in_game: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::bags::Entity",
+        from = "Column::BagId",
+        to = "super::bags::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Bags,
     #[sea_orm(
         belongs_to = "super::items::Entity",
         from = "Column::ItemId",
@@ -26,6 +42,20 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Items,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::DrawnBy",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    Users,
+}
+
+impl Related<super::bags::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bags.def()
+    }
 }
 
 impl Related<super::items::Entity> for Entity {
@@ -33,3 +63,9 @@ impl Related<super::items::Entity> for Entity {
         Relation::Items.def()
     }
 }
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
@@ -24,4 +24,37 @@ pub struct Model {
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::bag_invites::Entity")]
+    BagInvites,
+    #[sea_orm(has_many = "super::bag_members::Entity")]
+    BagMembers,
+    #[sea_orm(has_many = "super::bags::Entity")]
+    Bags,
+    #[sea_orm(has_many = "super::taken_items::Entity")]
+    TakenItems,
+}
+
+impl Related<super::bag_invites::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BagInvites.def()
+    }
+}
+
+impl Related<super::bag_members::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BagMembers.def()
+    }
+}
+
+impl Related<super::bags::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bags.def()
+    }
+}
+
+impl Related<super::taken_items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TakenItems.def()
+    }
+}
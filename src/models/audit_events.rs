@@ -0,0 +1,219 @@
+use chrono::Utc;
+use loco_rs::model::{ModelError, ModelResult};
+use sea_orm::{
+    entity::prelude::*, sea_query::Query, ActiveValue, ConnectionTrait, DatabaseConnection, DbErr, QueryOrder,
+};
+use serde::Serialize;
+
+pub use super::_entities::audit_events::{self, ActiveModel, Entity, Model};
+use super::_entities::{bag_members, bags, items, taken_items, users};
+
+/// Who made a change, recorded on its audit events.
+#[derive(Clone, Copy, Debug)]
+pub enum Actor<'a> {
+    User(&'a users::Model),
+    /// tasks and other changes no user asked for directly
+    System,
+}
+
+impl Actor<'_> {
+    #[must_use]
+    pub const fn user_id(&self) -> Option<i32> {
+        match self {
+            Self::User(user) => Some(user.id),
+            Self::System => None,
+        }
+    }
+
+    fn pid(&self) -> Option<String> {
+        match self {
+            Self::User(user) => Some(user.pid.to_string()),
+            Self::System => None,
+        }
+    }
+}
+
+/// Entities whose changes are written to the audit log.
+pub trait Audited: Serialize {
+    const ENTITY: interface::AuditEntity;
+
+    fn id(&self) -> i32;
+
+    fn bag_id(&self) -> i32;
+}
+
+impl Audited for items::Model {
+    const ENTITY: interface::AuditEntity = interface::AuditEntity::Item;
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn bag_id(&self) -> i32 {
+        self.bag_id
+    }
+}
+
+impl Audited for taken_items::Model {
+    const ENTITY: interface::AuditEntity = interface::AuditEntity::TakenItem;
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn bag_id(&self) -> i32 {
+        self.bag_id
+    }
+}
+
+impl Audited for bags::Model {
+    const ENTITY: interface::AuditEntity = interface::AuditEntity::Bag;
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn bag_id(&self) -> i32 {
+        self.id
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+
+        if insert {
+            this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+        }
+        this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+        Ok(this)
+    }
+}
+
+impl From<Model> for interface::AuditEvent {
+    fn from(value: Model) -> Self {
+        Self {
+            created_at: value.created_at,
+            id: value.id,
+            bag_id: value.bag_id,
+            actor_pid: value.actor_pid,
+            action: interface::AuditAction::from_repr(value.action).unwrap(),
+            entity: interface::AuditEntity::from_repr(value.entity).unwrap(),
+            entity_id: value.entity_id,
+            before: value.before,
+            after: value.after,
+        }
+    }
+}
+
+fn to_json<T: Serialize>(entity: Option<&T>) -> ModelResult<Option<Json>> {
+    entity
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|err| ModelError::Any(Box::new(err)))
+}
+
+impl Model {
+    /// Records that `actor` did `action` to an entity, given as it was before
+    /// and after the change. Changes are recorded on the connection making
+    /// them, so they are only kept when the change is.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn record<C: ConnectionTrait, T: Audited + Sync>(
+        db: &C,
+        actor: Actor<'_>,
+        action: interface::AuditAction,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> ModelResult<()> {
+        let Some(entity) = after.or(before) else {
+            return Ok(());
+        };
+        audit_events::ActiveModel {
+            bag_id: ActiveValue::Set(entity.bag_id()),
+            actor_pid: ActiveValue::Set(actor.pid()),
+            action: ActiveValue::Set(action as i16),
+            entity: ActiveValue::Set(T::ENTITY as i16),
+            entity_id: ActiveValue::Set(entity.id()),
+            before: ActiveValue::Set(to_json(before)?),
+            after: ActiveValue::Set(to_json(after)?),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Lists the changes made in the bags the user runs as a game master or
+    /// owner, newest first.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn list(
+        db: &DatabaseConnection,
+        user_id: i32,
+        filter: Option<interface::AuditFilter>,
+    ) -> ModelResult<interface::AuditPage> {
+        let filter = filter.unwrap_or_default();
+
+        let managed_bags = Query::select()
+            .column(bag_members::Column::BagId)
+            .from(bag_members::Entity)
+            .and_where(bag_members::Column::UserId.eq(user_id))
+            .and_where(bag_members::Column::Role.gte(interface::BagRole::GameMaster as i16))
+            .to_owned();
+        let mut query = audit_events::Entity::find()
+            .filter(audit_events::Column::BagId.in_subquery(managed_bags))
+            .order_by_desc(audit_events::Column::CreatedAt)
+            .order_by_desc(audit_events::Column::Id);
+        if let Some(bag_id) = filter.bag_id {
+            query = query.filter(audit_events::Column::BagId.eq(bag_id));
+        }
+        if let Some(actor) = filter.actor {
+            query = query.filter(audit_events::Column::ActorPid.eq(actor));
+        }
+        if let Some(entity) = filter.entity {
+            query = query.filter(audit_events::Column::Entity.eq(entity as i16));
+        }
+        if let Some(entity_id) = filter.entity_id {
+            query = query.filter(audit_events::Column::EntityId.eq(entity_id));
+        }
+        if let Some(from) = filter.from {
+            query = query.filter(audit_events::Column::CreatedAt.gte(from));
+        }
+        if let Some(to) = filter.to {
+            query = query.filter(audit_events::Column::CreatedAt.lt(to));
+        }
+
+        let page_size = if filter.page_size > 0 {
+            filter.page_size
+        } else {
+            50
+        };
+
+        let paginator = query.paginate(db, page_size);
+        let items_and_pages = paginator.num_items_and_pages().await?;
+        let events = paginator
+            .fetch_page(filter.page_num)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        Ok(interface::AuditPage {
+            events,
+            page_num: filter.page_num,
+            page_size,
+            total_pages: items_and_pages.number_of_pages,
+            total_results: items_and_pages.number_of_items,
+        })
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::Utc;
+use loco_rs::model::{ModelError, ModelResult};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, DbErr, QueryOrder, TransactionTrait};
+use uuid::Uuid;
+
+pub use super::_entities::bag_invites::{self, ActiveModel, Entity, Model};
+use super::bag_members;
+
+impl From<Model> for interface::Invite {
+    fn from(value: Model) -> Self {
+        Self {
+            created_at: value.created_at,
+            bag_id: value.bag_id,
+            token: value.token,
+            role: interface::BagRole::from_repr(value.role).unwrap_or(interface::BagRole::Viewer),
+            accepted_at: value.accepted_at,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+
+        if insert {
+            this.token = ActiveValue::Set(Uuid::new_v4().to_string());
+            this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+        }
+        this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+        Ok(this)
+    }
+}
+
+impl Model {
+    /// creates a single-use invite into a bag
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        bag_id: i32,
+        invited_by: i32,
+        role: interface::BagRole,
+    ) -> ModelResult<Self> {
+        Ok(bag_invites::ActiveModel {
+            bag_id: ActiveValue::Set(bag_id),
+            role: ActiveValue::Set(role as i16),
+            invited_by: ActiveValue::Set(invited_by),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    /// lists every invite created for a bag, newest first
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn list_for_bag(db: &DatabaseConnection, bag_id: i32) -> ModelResult<Vec<Self>> {
+        Ok(bag_invites::Entity::find()
+            .filter(bag_invites::Column::BagId.eq(bag_id))
+            .order_by_desc(bag_invites::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    /// accepts a pending invite, making the user a member of the bag
+    ///
+    /// # Errors
+    ///
+    /// When the invite does not exist or was already used, when the user is
+    /// already a member of the bag or DB query error
+    pub async fn accept(db: &DatabaseConnection, token: &str, user_id: i32) -> ModelResult<bag_members::Model> {
+        let txn = db.begin().await?;
+
+        let invite = bag_invites::Entity::find()
+            .filter(bag_invites::Column::Token.eq(token))
+            .filter(bag_invites::Column::AcceptedAt.is_null())
+            .one(&txn)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+
+        let role = interface::BagRole::from_repr(invite.role).unwrap_or(interface::BagRole::Viewer);
+        let member = bag_members::Model::add(&txn, invite.bag_id, user_id, role).await?;
+
+        bag_invites::ActiveModel {
+            id: ActiveValue::Unchanged(invite.id),
+            accepted_by: ActiveValue::Set(Some(user_id)),
+            accepted_at: ActiveValue::Set(Some(Utc::now().naive_utc())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(member)
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::Utc;
+use loco_rs::model::{ModelError, ModelResult};
+use sea_orm::{entity::prelude::*, ActiveValue, ConnectionTrait, DatabaseConnection, DbErr, QueryOrder};
+
+pub use super::_entities::bag_members::{self, ActiveModel, Entity, Model};
+use super::_entities::users;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+
+        if insert {
+            this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+        }
+        this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+        Ok(this)
+    }
+}
+
+impl Model {
+    #[must_use]
+    pub fn role(&self) -> interface::BagRole {
+        interface::BagRole::from_repr(self.role).unwrap_or(interface::BagRole::Viewer)
+    }
+
+    /// finds the membership of a user in a bag
+    ///
+    /// # Errors
+    ///
+    /// When the user is not a member of the bag or DB query error
+    pub async fn find_membership<C: ConnectionTrait>(db: &C, bag_id: i32, user_id: i32) -> ModelResult<Self> {
+        let member = bag_members::Entity::find()
+            .filter(bag_members::Column::BagId.eq(bag_id))
+            .filter(bag_members::Column::UserId.eq(user_id))
+            .one(db)
+            .await?;
+        member.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// lists the members of a bag together with their users
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn list_for_bag(db: &DatabaseConnection, bag_id: i32) -> ModelResult<Vec<interface::BagMember>> {
+        Ok(bag_members::Entity::find()
+            .filter(bag_members::Column::BagId.eq(bag_id))
+            .order_by_desc(bag_members::Column::Role)
+            .order_by_asc(bag_members::Column::Id)
+            .find_also_related(users::Entity)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(member, user)| {
+                user.map(|user| interface::BagMember {
+                    user_pid: user.pid.to_string(),
+                    name: user.name,
+                    role: member.role(),
+                })
+            })
+            .collect())
+    }
+
+    /// adds a user to a bag with the given role
+    ///
+    /// # Errors
+    ///
+    /// When the user is already a member of the bag or DB query error
+    pub async fn add<C: ConnectionTrait>(
+        db: &C,
+        bag_id: i32,
+        user_id: i32,
+        role: interface::BagRole,
+    ) -> ModelResult<Self> {
+        if Self::find_membership(db, bag_id, user_id).await.is_ok() {
+            return Err(ModelError::EntityAlreadyExists {});
+        }
+
+        Ok(bag_members::ActiveModel {
+            bag_id: ActiveValue::Set(bag_id),
+            user_id: ActiveValue::Set(user_id),
+            role: ActiveValue::Set(role as i16),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    /// changes the role of a bag member
+    ///
+    /// # Errors
+    ///
+    /// When the user is not a member of the bag or DB query error
+    pub async fn update_role(
+        db: &DatabaseConnection,
+        bag_id: i32,
+        user_id: i32,
+        role: interface::BagRole,
+    ) -> ModelResult<Self> {
+        let member = Self::find_membership(db, bag_id, user_id).await?;
+        Ok(bag_members::ActiveModel {
+            id: ActiveValue::Unchanged(member.id),
+            role: ActiveValue::Set(role as i16),
+            ..Default::default()
+        }
+        .update(db)
+        .await?)
+    }
+
+    /// removes a user from a bag
+    ///
+    /// # Errors
+    ///
+    /// When the user is not a member of the bag or DB query error
+    pub async fn remove(db: &DatabaseConnection, bag_id: i32, user_id: i32) -> ModelResult<()> {
+        let member = Self::find_membership(db, bag_id, user_id).await?;
+        bag_members::Entity::delete_by_id(member.id).exec(db).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+use chrono::Utc;
+use loco_rs::{
+    model::{ModelError, ModelResult},
+    validation,
+    validator::Validate,
+};
+use sea_orm::{entity::prelude::*, sea_query::Query, ActiveValue, DatabaseConnection, DbErr, QueryOrder, TransactionTrait};
+use serde::Deserialize;
+
+use crate::dice::DiceExpression;
+pub use super::_entities::bags::{self, ActiveModel, Entity, Model};
+use super::_entities::{bag_invites, bag_members, draw_transitions, game_rounds, item_tags, items, tags, taken_items};
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct ModelValidator {
+    #[validate(length(min = 1, message = "Name must be at least 1 character long"))]
+    pub name: String,
+    #[validate(custom = "crate::dice::validate_expression")]
+    pub rounds_dice: Option<String>,
+    #[validate(range(min = 1, max = 20, message = "A hand holds between 1 and 20 draws"))]
+    pub hand_size: Option<i16>,
+    #[validate(range(min = 0, message = "The reroll budget cannot be negative"))]
+    pub reroll_budget: Option<i16>,
+}
+
+impl From<&ActiveModel> for ModelValidator {
+    fn from(value: &ActiveModel) -> Self {
+        Self {
+            name: value.name.as_ref().to_string(),
+            rounds_dice: value.rounds_dice.as_ref().clone(),
+            hand_size: *value.hand_size.as_ref(),
+            reroll_budget: *value.reroll_budget.as_ref(),
+        }
+    }
+}
+
+impl From<Model> for interface::Bag {
+    fn from(value: Model) -> Self {
+        Self {
+            draw_strategy: value.draw_strategy(),
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            id: value.id,
+            name: value.name,
+            auto_restock: value.auto_restock,
+            rounds_dice: value.rounds_dice,
+            hand_size: value.hand_size,
+            reroll_budget: value.reroll_budget,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        let mut this = self;
+
+        if insert {
+            this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+        }
+        this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+        Ok(this)
+    }
+}
+
+impl Model {
+    /// the draw strategy picked for this bag, if any
+    #[must_use]
+    pub fn draw_strategy(&self) -> Option<interface::DrawStrategyKind> {
+        self.draw_strategy.and_then(interface::DrawStrategyKind::from_repr)
+    }
+
+    /// the dice rolled for the rounds of items that set none of their own, if any
+    #[must_use]
+    pub fn rounds_dice(&self) -> Option<DiceExpression> {
+        self.rounds_dice.as_ref().and_then(|dice| dice.parse().ok())
+    }
+
+    /// finds a bag by its id
+    ///
+    /// # Errors
+    ///
+    /// When the bag does not exist or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: i32) -> ModelResult<Self> {
+        let bag = bags::Entity::find_by_id(id).one(db).await?;
+        bag.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// finds a bag the given user is a member of
+    ///
+    /// # Errors
+    ///
+    /// When the bag does not exist, the user is not a member or DB query error
+    pub async fn find_for_user(db: &DatabaseConnection, id: i32, user_id: i32) -> ModelResult<Self> {
+        let bag = bags::Entity::find()
+            .inner_join(bag_members::Entity)
+            .filter(bags::Column::Id.eq(id))
+            .filter(bag_members::Column::UserId.eq(user_id))
+            .one(db)
+            .await?;
+        bag.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// lists every bag the given user is a member of
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn list_for_user(db: &DatabaseConnection, user_id: i32) -> ModelResult<Vec<Self>> {
+        Ok(bags::Entity::find()
+            .inner_join(bag_members::Entity)
+            .filter(bag_members::Column::UserId.eq(user_id))
+            .order_by_asc(bags::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    /// creates a new bag owned by the given user
+    ///
+    /// # Errors
+    ///
+    /// When the bag is not valid or DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        user_id: i32,
+        create: interface::CreateUpdateBag,
+    ) -> ModelResult<Self> {
+        let txn = db.begin().await?;
+        let bag = bags::ActiveModel {
+            name: ActiveValue::Set(create.name),
+            user_id: ActiveValue::Set(user_id),
+            draw_strategy: ActiveValue::Set(create.draw_strategy.map(|kind| kind as i16)),
+            auto_restock: ActiveValue::Set(create.auto_restock),
+            rounds_dice: ActiveValue::Set(create.rounds_dice),
+            hand_size: ActiveValue::Set(create.hand_size),
+            reroll_budget: ActiveValue::Set(create.reroll_budget),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        bag_members::Model::add(&txn, bag.id, user_id, interface::BagRole::Owner).await?;
+
+        txn.commit().await?;
+        Ok(bag)
+    }
+
+    /// updates the name and draw settings of the bag
+    ///
+    /// # Errors
+    ///
+    /// When the bag is not valid or DB query error
+    pub async fn update(
+        db: &DatabaseConnection,
+        id: i32,
+        update: interface::CreateUpdateBag,
+    ) -> ModelResult<Self> {
+        let bag = bags::ActiveModel {
+            id: ActiveValue::Unchanged(id),
+            name: ActiveValue::Set(update.name),
+            draw_strategy: ActiveValue::Set(update.draw_strategy.map(|kind| kind as i16)),
+            auto_restock: ActiveValue::Set(update.auto_restock),
+            rounds_dice: ActiveValue::Set(update.rounds_dice),
+            hand_size: ActiveValue::Set(update.hand_size),
+            reroll_budget: ActiveValue::Set(update.reroll_budget),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+        Ok(bag)
+    }
+
+    /// deletes the bag together with its items, tags, draws, rounds and members
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn delete(db: &DatabaseConnection, id: i32) -> ModelResult<()> {
+        let txn = db.begin().await?;
+        bag_invites::Entity::delete_many()
+            .filter(bag_invites::Column::BagId.eq(id))
+            .exec(&txn)
+            .await?;
+        bag_members::Entity::delete_many()
+            .filter(bag_members::Column::BagId.eq(id))
+            .exec(&txn)
+            .await?;
+        draw_transitions::Entity::delete_many()
+            .filter(draw_transitions::Column::BagId.eq(id))
+            .exec(&txn)
+            .await?;
+        game_rounds::Entity::delete_many()
+            .filter(game_rounds::Column::BagId.eq(id))
+            .exec(&txn)
+            .await?;
+        taken_items::Entity::delete_many()
+            .filter(taken_items::Column::BagId.eq(id))
+            .exec(&txn)
+            .await?;
+        item_tags::Entity::delete_many()
+            .filter(
+                item_tags::Column::TagId.in_subquery(
+                    Query::select()
+                        .column(tags::Column::Id)
+                        .from(tags::Entity)
+                        .and_where(tags::Column::BagId.eq(id))
+                        .to_owned(),
+                ),
+            )
+            .exec(&txn)
+            .await?;
+        tags::Entity::delete_many()
+            .filter(tags::Column::BagId.eq(id))
+            .exec(&txn)
+            .await?;
+        items::Entity::delete_many()
+            .filter(items::Column::BagId.eq(id))
+            .exec(&txn)
+            .await?;
+        bags::Entity::delete_by_id(id).exec(&txn).await?;
+
+        txn.commit().await?;
+        Ok(())
+    }
+}
+
+impl ActiveModel {
+    /// Validate bag schema
+    ///
+    /// # Errors
+    ///
+    /// when the active model is not valid
+    pub fn validate(&self) -> Result<(), DbErr> {
+        let validator: ModelValidator = self.into();
+        validator
+            .validate()
+            .map_err(|e| validation::into_db_error(&e))
+    }
+}
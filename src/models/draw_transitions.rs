@@ -0,0 +1,269 @@
+use std::fmt;
+use chrono::Utc;
+use loco_rs::model::{ModelError, ModelResult};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, ConnectionTrait, DatabaseConnection, DbErr, IntoActiveModel, QueryOrder, QuerySelect,
+    SqlErr, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::draw_transitions::{self, ActiveModel, Entity, Model};
+use super::_entities::taken_items;
+use super::audit_events::{self, Actor};
+
+/// Errors of undoing or redoing a change to the draws of a bag.
+#[derive(Debug)]
+pub enum HistoryError {
+    /// the bag has no change left to undo or redo
+    Empty(i32),
+    /// a draw of the change was changed outside the history since, or
+    /// another draw took its slot in the hand
+    Conflict(i32),
+    /// the newest change advanced the bag to its next round, which is final
+    RoundAdvanced(i32),
+    Model(ModelError),
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty(bag_id) => write!(f, "no changes to the draws of bag {bag_id} left"),
+            Self::Conflict(bag_id) => write!(f, "the draws of bag {bag_id} changed since"),
+            Self::RoundAdvanced(bag_id) => write!(f, "bag {bag_id} advanced to its next round since"),
+            Self::Model(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+impl From<ModelError> for HistoryError {
+    fn from(value: ModelError) -> Self {
+        Self::Model(value)
+    }
+}
+
+impl From<DbErr> for HistoryError {
+    fn from(value: DbErr) -> Self {
+        Self::Model(value.into())
+    }
+}
+
+/// The part of a draw that changes move between.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+struct DrawState {
+    rounds_left: i16,
+    done: bool,
+    #[serde(default)]
+    paused: bool,
+}
+
+impl From<&taken_items::Model> for DrawState {
+    fn from(value: &taken_items::Model) -> Self {
+        Self {
+            rounds_left: value.rounds_left,
+            done: value.done,
+            paused: value.paused,
+        }
+    }
+}
+
+/// What a change did to one of its draws.
+#[derive(Serialize, Deserialize, Debug)]
+struct DrawChange {
+    taken_item_id: i32,
+    before: DrawState,
+    after: DrawState,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+
+        if insert {
+            this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+        }
+        this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+        Ok(this)
+    }
+}
+
+impl Model {
+    fn changes(&self) -> ModelResult<Vec<DrawChange>> {
+        serde_json::from_value(self.changes.clone()).map_err(|err| ModelError::Any(Box::new(err)))
+    }
+
+    /// Records that `action` changed the draws of the bag, given as they were
+    /// before and after, as the change to undo next. Undone changes can no
+    /// longer be redone afterwards, and only the newest `history_depth`
+    /// changes are kept. Advancing the round is recorded even when it changed
+    /// no draw, so that undo always stops at it.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn record<C: ConnectionTrait>(
+        db: &C,
+        bag_id: i32,
+        action: interface::AuditAction,
+        changed: &[(taken_items::Model, taken_items::Model)],
+        history_depth: u64,
+    ) -> ModelResult<()> {
+        if changed.is_empty() && action != interface::AuditAction::Advance {
+            return Ok(());
+        }
+        let changes: Vec<DrawChange> = changed
+            .iter()
+            .map(|(before, after)| DrawChange {
+                taken_item_id: after.id,
+                before: before.into(),
+                after: after.into(),
+            })
+            .collect();
+        let changes = serde_json::to_value(changes).map_err(|err| ModelError::Any(Box::new(err)))?;
+
+        draw_transitions::Entity::delete_many()
+            .filter(draw_transitions::Column::BagId.eq(bag_id))
+            .filter(draw_transitions::Column::Undone.eq(true))
+            .exec(db)
+            .await?;
+        draw_transitions::ActiveModel {
+            bag_id: ActiveValue::Set(bag_id),
+            action: ActiveValue::Set(action as i16),
+            changes: ActiveValue::Set(changes),
+            undone: ActiveValue::Set(false),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        let oldest_kept = draw_transitions::Entity::find()
+            .filter(draw_transitions::Column::BagId.eq(bag_id))
+            .order_by_desc(draw_transitions::Column::Id)
+            .offset(history_depth.saturating_sub(1))
+            .one(db)
+            .await?;
+        if let Some(oldest_kept) = oldest_kept {
+            draw_transitions::Entity::delete_many()
+                .filter(draw_transitions::Column::BagId.eq(bag_id))
+                .filter(draw_transitions::Column::Id.lt(oldest_kept.id))
+                .exec(db)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Puts the draws of the newest change of the bag back the way they were
+    /// before it. Advancing the round of a bag is final: it stays in the
+    /// history so that neither it nor the changes before it can be undone.
+    ///
+    /// # Errors
+    ///
+    /// When there is nothing to undo, the newest change advanced the round, a
+    /// draw of the change was changed since, or DB query error
+    pub async fn undo(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32) -> Result<interface::DrawTransition, HistoryError> {
+        Self::step(db, actor, bag_id, true).await
+    }
+
+    /// Makes the change undone last again.
+    ///
+    /// # Errors
+    ///
+    /// When there is nothing to redo, a draw of the change was changed since,
+    /// or DB query error
+    pub async fn redo(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32) -> Result<interface::DrawTransition, HistoryError> {
+        Self::step(db, actor, bag_id, false).await
+    }
+
+    async fn step(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, undo: bool) -> Result<interface::DrawTransition, HistoryError> {
+        let txn = db.begin().await?;
+        // Recording a change forgets the undone ones, so undone changes are
+        // always the newest: undo the newest change that is not undone yet,
+        // redo the oldest one that is.
+        let query = draw_transitions::Entity::find()
+            .filter(draw_transitions::Column::BagId.eq(bag_id))
+            .filter(draw_transitions::Column::Undone.eq(!undo));
+        let query = if undo {
+            query.order_by_desc(draw_transitions::Column::Id)
+        } else {
+            query.order_by_asc(draw_transitions::Column::Id)
+        };
+        let Some(transition) = query.one(&txn).await? else {
+            return Err(HistoryError::Empty(bag_id));
+        };
+        if transition.action == interface::AuditAction::Advance as i16 {
+            return Err(HistoryError::RoundAdvanced(bag_id));
+        }
+
+        let mut changes = transition.changes()?;
+        if undo {
+            changes.reverse();
+        }
+        let action = if undo {
+            interface::AuditAction::Undo
+        } else {
+            interface::AuditAction::Redo
+        };
+        let mut taken = vec![];
+        for change in changes {
+            let (from, to) = if undo {
+                (change.after, change.before)
+            } else {
+                (change.before, change.after)
+            };
+            // restocked and rerolled draws are back in the bag and no longer
+            // ours to change
+            let Some(before) = taken_items::Entity::find_by_id(change.taken_item_id)
+                .filter(taken_items::Column::BagId.eq(bag_id))
+                .one(&txn)
+                .await?
+                .filter(|taken| !taken.restocked && !taken.rerolled && DrawState::from(taken) == from)
+            else {
+                return Err(HistoryError::Conflict(bag_id));
+            };
+            let mut itm = before.clone().into_active_model();
+            itm.rounds_left = ActiveValue::Set(to.rounds_left);
+            itm.done = ActiveValue::Set(to.done);
+            itm.paused = ActiveValue::Set(to.paused);
+            let after = match itm.update(&txn).await {
+                Ok(after) => after,
+                // a newer draw took the slot of a draw made active again
+                Err(err) if matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+                    return Err(HistoryError::Conflict(bag_id));
+                }
+                Err(err) => return Err(err.into()),
+            };
+            audit_events::Model::record(&txn, actor, action, Some(&before), Some(&after)).await?;
+            taken.push(after);
+        }
+        if undo {
+            taken.reverse();
+        }
+
+        // a concurrent undo or redo may have stepped over the same change
+        let stepped = draw_transitions::Entity::update_many()
+            .col_expr(draw_transitions::Column::Undone, Expr::value(undo))
+            .filter(draw_transitions::Column::Id.eq(transition.id))
+            .filter(draw_transitions::Column::Undone.eq(!undo))
+            .exec(&txn)
+            .await?;
+        if stepped.rows_affected == 0 {
+            return Err(HistoryError::Conflict(bag_id));
+        }
+        txn.commit().await?;
+
+        Ok(interface::DrawTransition {
+            created_at: transition.created_at,
+            id: transition.id,
+            bag_id,
+            action: interface::AuditAction::from_repr(transition.action).unwrap(),
+            undone: undo,
+            taken: taken.into_iter().map(Into::into).collect(),
+        })
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::fmt;
+use chrono::Utc;
+use loco_rs::model::{ModelError, ModelResult};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbErr, QueryOrder, SqlErr,
+    TransactionTrait,
+};
+
+pub use super::_entities::game_rounds::{self, ActiveModel, Entity, Model};
+use super::_entities::items;
+use super::audit_events::{self, Actor, Audited};
+use super::taken_items;
+
+/// Errors of advancing a bag to its next round or game.
+#[derive(Debug)]
+pub enum AdvanceError {
+    /// the bag was advanced to the same round or game at the same time
+    Concurrent(i32),
+    Model(ModelError),
+}
+
+impl fmt::Display for AdvanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Concurrent(bag_id) => write!(f, "bag {bag_id} was advanced at the same time"),
+            Self::Model(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for AdvanceError {}
+
+impl From<ModelError> for AdvanceError {
+    fn from(value: ModelError) -> Self {
+        Self::Model(value)
+    }
+}
+
+impl From<DbErr> for AdvanceError {
+    fn from(value: DbErr) -> Self {
+        Self::Model(value.into())
+    }
+}
+
+impl Audited for Model {
+    const ENTITY: interface::AuditEntity = interface::AuditEntity::GameRound;
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn bag_id(&self) -> i32 {
+        self.bag_id
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+
+        if insert {
+            this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+        }
+        this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+        Ok(this)
+    }
+}
+
+impl From<Model> for interface::GameRound {
+    fn from(value: Model) -> Self {
+        Self {
+            bag_id: value.bag_id,
+            game: value.game,
+            round: value.number,
+            started_at: Some(value.created_at),
+        }
+    }
+}
+
+impl Model {
+    async fn find_latest<C: ConnectionTrait>(db: &C, bag_id: i32) -> Result<Option<Self>, DbErr> {
+        game_rounds::Entity::find()
+            .filter(game_rounds::Column::BagId.eq(bag_id))
+            .order_by_desc(game_rounds::Column::Game)
+            .order_by_desc(game_rounds::Column::Number)
+            .one(db)
+            .await
+    }
+
+    /// The game the bag is playing, game 1 until a new one is started.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn current_game<C: ConnectionTrait>(db: &C, bag_id: i32) -> Result<i32, DbErr> {
+        Ok(Self::find_latest(db, bag_id).await?.map_or(1, |latest| latest.game))
+    }
+
+    /// Saves the round on the given transaction, turning a round someone
+    /// else reached at the same time into [`AdvanceError::Concurrent`].
+    async fn insert_round(txn: &DatabaseTransaction, actor: Actor<'_>, bag_id: i32, game: i32, number: i32) -> Result<Self, AdvanceError> {
+        let inserted = game_rounds::ActiveModel {
+            bag_id: ActiveValue::Set(bag_id),
+            game: ActiveValue::Set(game),
+            number: ActiveValue::Set(number),
+            advanced_by: ActiveValue::Set(actor.user_id()),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await;
+        match inserted {
+            Ok(round) => Ok(round),
+            // the unique index on rounds rejected the second of two presses
+            Err(err) if matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+                Err(AdvanceError::Concurrent(bag_id))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// The round the bag is in, round 0 until it is first advanced.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn current(db: &DatabaseConnection, bag_id: i32) -> ModelResult<interface::GameRound> {
+        Ok(Self::find_latest(db, bag_id).await?.map_or(
+            interface::GameRound {
+                bag_id,
+                game: 1,
+                round: 0,
+                started_at: None,
+            },
+            Into::into,
+        ))
+    }
+
+    /// Advances the bag to its next round and takes a round off every active
+    /// draw in the same transaction. Draws that run out of rounds are done
+    /// and listed as expired along with their items.
+    ///
+    /// # Errors
+    ///
+    /// When the bag is advanced by another request at the same time, or DB
+    /// query error
+    pub async fn advance(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, history_depth: u64) -> Result<interface::RoundSummary, AdvanceError> {
+        let txn = db.begin().await?;
+        let (game, number) = Self::find_latest(&txn, bag_id)
+            .await?
+            .map_or((1, 1), |latest| (latest.game, latest.number + 1));
+        let round = Self::insert_round(&txn, actor, bag_id, game, number).await?;
+        audit_events::Model::record(&txn, actor, interface::AuditAction::Advance, None, Some(&round)).await?;
+        let advanced = taken_items::Model::advance_hand(&txn, actor, bag_id, history_depth).await?;
+        txn.commit().await?;
+
+        let (expired, active): (Vec<_>, Vec<_>) = advanced.into_iter().partition(|taken| taken.done);
+        let expired_items = items::Entity::find()
+            .filter(items::Column::Id.is_in(expired.iter().map(|taken| taken.item_id)))
+            .all(db)
+            .await?;
+        let expired_items: HashMap<i32, interface::Item> = items::Model::with_tags(db, expired_items)
+            .await?
+            .into_iter()
+            .map(|item| (item.id, item))
+            .collect();
+        let expired = expired
+            .into_iter()
+            .map(|taken| {
+                let item = expired_items.get(&taken.item_id).cloned().ok_or(ModelError::EntityNotFound)?;
+                Ok(interface::ExpiredDraw {
+                    taken: taken.into(),
+                    item,
+                })
+            })
+            .collect::<ModelResult<_>>()?;
+
+        Ok(interface::RoundSummary {
+            round: round.into(),
+            active: active.into_iter().map(Into::into).collect(),
+            expired,
+        })
+    }
+
+    /// Starts a new game in the bag at round 0. The draws in the hand stay
+    /// where they are, but the bag gets its whole reroll budget back.
+    ///
+    /// # Errors
+    ///
+    /// When a new game is started in the bag by another request at the same
+    /// time, or DB query error
+    pub async fn new_game(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32) -> Result<interface::GameRound, AdvanceError> {
+        let txn = db.begin().await?;
+        let game = Self::current_game(&txn, bag_id).await? + 1;
+        let round = Self::insert_round(&txn, actor, bag_id, game, 0).await?;
+        audit_events::Model::record(&txn, actor, interface::AuditAction::NewGame, None, Some(&round)).await?;
+        txn.commit().await?;
+        Ok(round.into())
+    }
+}
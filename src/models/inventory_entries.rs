@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::fmt;
+use chrono::Utc;
+use loco_rs::model::{ModelError, ModelResult};
+use sea_orm::{
+    entity::prelude::*, sea_query::{Query, SimpleExpr}, ActiveValue, Condition, ConnectionTrait, DatabaseConnection,
+    DbErr, FromQueryResult, QueryOrder, QuerySelect, TransactionTrait,
+};
+
+pub use super::_entities::inventory_entries::{self, ActiveModel, Entity, Model};
+use super::_entities::{items, taken_items};
+use super::audit_events::{self, Actor, Audited};
+
+/// Errors of changing the stock of an item by hand.
+#[derive(Debug)]
+pub enum StockError {
+    /// the change would leave less than nothing, holds the current stock
+    Insufficient(i64),
+    /// the amount to change the stock by makes no sense
+    InvalidAmount(i32),
+    Model(ModelError),
+}
+
+impl fmt::Display for StockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Insufficient(balance) => write!(f, "only {balance} left in stock"),
+            Self::InvalidAmount(amount) => write!(f, "cannot change the stock by {amount}"),
+            Self::Model(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StockError {}
+
+impl From<ModelError> for StockError {
+    fn from(value: ModelError) -> Self {
+        Self::Model(value)
+    }
+}
+
+impl From<DbErr> for StockError {
+    fn from(value: DbErr) -> Self {
+        Self::Model(value.into())
+    }
+}
+
+impl Audited for Model {
+    const ENTITY: interface::AuditEntity = interface::AuditEntity::InventoryEntry;
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn bag_id(&self) -> i32 {
+        self.bag_id
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+
+        if insert {
+            this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+        }
+        this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+        Ok(this)
+    }
+}
+
+impl From<Model> for interface::InventoryEntry {
+    fn from(value: Model) -> Self {
+        Self {
+            created_at: value.created_at,
+            id: value.id,
+            item_id: value.item_id,
+            kind: interface::InventoryKind::from_repr(value.kind).unwrap(),
+            delta: value.delta,
+            reason: value.reason,
+            taken_item_id: value.taken_item_id,
+        }
+    }
+}
+
+#[derive(FromQueryResult)]
+struct Balance {
+    item_id: i32,
+    balance: i64,
+}
+
+#[derive(FromQueryResult)]
+struct Held {
+    item_id: i32,
+    held: i64,
+}
+
+impl Model {
+    /// Writes an entry changing the stock of `item` by `delta`.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn record<C: ConnectionTrait>(
+        db: &C,
+        item: &items::Model,
+        kind: interface::InventoryKind,
+        delta: i32,
+        reason: Option<String>,
+        taken_item_id: Option<i32>,
+    ) -> Result<Self, DbErr> {
+        inventory_entries::ActiveModel {
+            bag_id: ActiveValue::Set(item.bag_id),
+            item_id: ActiveValue::Set(item.id),
+            kind: ActiveValue::Set(kind as i16),
+            delta: ActiveValue::Set(delta),
+            reason: ActiveValue::Set(reason),
+            taken_item_id: ActiveValue::Set(taken_item_id),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Sums up the ledger of each of the given items. Items without any entry
+    /// are left out.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn balances<C: ConnectionTrait>(db: &C, item_ids: Vec<i32>) -> Result<HashMap<i32, i64>, DbErr> {
+        Ok(inventory_entries::Entity::find()
+            .select_only()
+            .column(inventory_entries::Column::ItemId)
+            .column_as(inventory_entries::Column::Delta.sum(), "balance")
+            .filter(inventory_entries::Column::ItemId.is_in(item_ids))
+            .group_by(inventory_entries::Column::ItemId)
+            .into_model::<Balance>()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|balance| (balance.item_id, balance.balance))
+            .collect())
+    }
+
+    /// Sums up the ledger of the item.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn balance<C: ConnectionTrait>(db: &C, item_id: i32) -> Result<i64, DbErr> {
+        Ok(Self::balances(db, vec![item_id]).await?.get(&item_id).copied().unwrap_or(0))
+    }
+
+    /// Counts the units of each of the given items that active or paused
+    /// draws still hold. Items without any such draw are left out.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn held<C: ConnectionTrait>(db: &C, item_ids: Vec<i32>) -> Result<HashMap<i32, i64>, DbErr> {
+        Ok(taken_items::Entity::find()
+            .select_only()
+            .column(taken_items::Column::ItemId)
+            .column_as(taken_items::Column::Id.count(), "held")
+            .filter(taken_items::Column::ItemId.is_in(item_ids))
+            .filter(taken_items::Column::Done.eq(false))
+            .group_by(taken_items::Column::ItemId)
+            .into_model::<Held>()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|held| (held.item_id, held.held))
+            .collect())
+    }
+
+    /// How many units of `item` it takes to get back to its quantity, counting
+    /// the units in stock and those still held by unfinished draws.
+    fn missing(item: &items::Model, balance: i64, held: i64) -> i32 {
+        i32::try_from((i64::from(item.quantity) - balance - held).max(0)).unwrap_or(i32::MAX)
+    }
+
+    /// Items that can still be drawn: infinite ones, and the others while
+    /// their ledger holds at least one of them.
+    #[must_use]
+    pub fn available_condition() -> Condition {
+        let balance = Query::select()
+            .expr(Expr::col((inventory_entries::Entity, inventory_entries::Column::Delta)).sum())
+            .from(inventory_entries::Entity)
+            .and_where(
+                Expr::col((inventory_entries::Entity, inventory_entries::Column::ItemId))
+                    .equals((items::Entity, items::Column::Id)),
+            )
+            .to_owned();
+        Condition::any()
+            .add(Expr::col((items::Entity, items::Column::Infinite)).eq(true))
+            .add(Expr::expr(SimpleExpr::SubQuery(None, Box::new(balance.into_sub_query_statement()))).gte(1))
+    }
+
+    /// Refills every finite item of the bag up to its quantity. Units still
+    /// held by active or paused draws are not refilled.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn restock_bag<C: ConnectionTrait>(db: &C, bag_id: i32) -> Result<(), DbErr> {
+        let items = items::Entity::find()
+            .filter(items::Column::BagId.eq(bag_id))
+            .filter(items::Column::Infinite.eq(false))
+            .filter(items::Column::DeletedAt.is_null())
+            .all(db)
+            .await?;
+        let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+        let balances = Self::balances(db, ids.clone()).await?;
+        let held = Self::held(db, ids).await?;
+        for item in &items {
+            let missing = Self::missing(
+                item,
+                balances.get(&item.id).copied().unwrap_or(0),
+                held.get(&item.id).copied().unwrap_or(0),
+            );
+            if missing > 0 {
+                Self::record(db, item, interface::InventoryKind::Restock, missing, None, None).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts items back in stock: `amount` of them, or by default as many as
+    /// it takes to get back to the quantity of the item, leaving out the
+    /// units still held by active or paused draws.
+    ///
+    /// # Errors
+    ///
+    /// When the item is not in the bag, the amount is not positive, or DB query error
+    pub async fn restock(
+        db: &DatabaseConnection,
+        actor: Actor<'_>,
+        bag_id: i32,
+        item_id: i32,
+        restock: interface::Restock,
+    ) -> Result<items::Model, StockError> {
+        let txn = db.begin().await?;
+        let item = find_item(&txn, bag_id, item_id).await?;
+
+        let amount = match restock.amount {
+            Some(amount) if amount < 1 => return Err(StockError::InvalidAmount(amount)),
+            Some(amount) => amount,
+            None => {
+                let held = Self::held(&txn, vec![item.id]).await?.get(&item.id).copied().unwrap_or(0);
+                Self::missing(&item, Self::balance(&txn, item.id).await?, held)
+            }
+        };
+        if amount > 0 {
+            let entry = Self::record(&txn, &item, interface::InventoryKind::Restock, amount, restock.reason, None).await?;
+            audit_events::Model::record(&txn, actor, interface::AuditAction::Restock, None, Some(&entry)).await?;
+        }
+        txn.commit().await?;
+        Ok(item)
+    }
+
+    /// Corrects the stock of the item, which cannot go below zero.
+    ///
+    /// # Errors
+    ///
+    /// When the item is not in the bag, the change is zero or leaves less
+    /// than nothing, or DB query error
+    pub async fn adjust(
+        db: &DatabaseConnection,
+        actor: Actor<'_>,
+        bag_id: i32,
+        item_id: i32,
+        adjust: interface::AdjustStock,
+    ) -> Result<items::Model, StockError> {
+        if adjust.delta == 0 {
+            return Err(StockError::InvalidAmount(adjust.delta));
+        }
+        let txn = db.begin().await?;
+        let item = find_item(&txn, bag_id, item_id).await?;
+
+        let balance = Self::balance(&txn, item.id).await?;
+        if balance + i64::from(adjust.delta) < 0 {
+            return Err(StockError::Insufficient(balance));
+        }
+        let kind = if adjust.delta > 0 {
+            interface::InventoryKind::Add
+        } else {
+            interface::InventoryKind::Remove
+        };
+        let entry = Self::record(&txn, &item, kind, adjust.delta, Some(adjust.reason), None).await?;
+        audit_events::Model::record(&txn, actor, interface::AuditAction::Adjust, None, Some(&entry)).await?;
+        txn.commit().await?;
+        Ok(item)
+    }
+
+    /// Lists the ledger of the item, newest first.
+    ///
+    /// # Errors
+    ///
+    /// When the item is not in the bag, or DB query error
+    pub async fn list(db: &DatabaseConnection, bag_id: i32, item_id: i32) -> ModelResult<Vec<interface::InventoryEntry>> {
+        let item = find_item(db, bag_id, item_id).await?;
+        Ok(inventory_entries::Entity::find()
+            .filter(inventory_entries::Column::ItemId.eq(item.id))
+            .order_by_desc(inventory_entries::Column::CreatedAt)
+            .order_by_desc(inventory_entries::Column::Id)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+}
+
+/// loads an item of the bag that is not archived
+async fn find_item<C: ConnectionTrait>(db: &C, bag_id: i32, item_id: i32) -> ModelResult<items::Model> {
+    items::Entity::find()
+        .filter(items::Column::Id.eq(item_id))
+        .filter(items::Column::BagId.eq(bag_id))
+        .filter(items::Column::DeletedAt.is_null())
+        .one(db)
+        .await?
+        .ok_or(ModelError::EntityNotFound)
+}
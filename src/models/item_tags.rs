@@ -0,0 +1,54 @@
+use chrono::Utc;
+use loco_rs::model::ModelResult;
+use sea_orm::{entity::prelude::*, ActiveValue, ConnectionTrait, DbErr};
+
+pub use super::_entities::item_tags::{self, ActiveModel, Entity, Model};
+use super::tags;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+
+        if insert {
+            this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+        }
+        this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+        Ok(this)
+    }
+}
+
+impl Model {
+    /// replaces the tags of an item, creating the tags missing from the bag
+    ///
+    /// # Errors
+    ///
+    /// When a tag name is not valid or DB query error
+    pub async fn set_for_item<C: ConnectionTrait>(
+        db: &C,
+        bag_id: i32,
+        item_id: i32,
+        names: &[String],
+    ) -> ModelResult<Vec<tags::Model>> {
+        item_tags::Entity::delete_many()
+            .filter(item_tags::Column::ItemId.eq(item_id))
+            .exec(db)
+            .await?;
+
+        let tags = tags::Model::find_or_create(db, bag_id, names).await?;
+        for tag in &tags {
+            item_tags::ActiveModel {
+                item_id: ActiveValue::Set(item_id),
+                tag_id: ActiveValue::Set(tag.id),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+        }
+        Ok(tags)
+    }
+}
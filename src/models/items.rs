@@ -1,14 +1,21 @@
-use chrono::Utc;
-use sea_orm::entity::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use chrono::{NaiveDateTime, Utc};
 pub use super::_entities::items::{self, Entity, ActiveModel, Model};
+use super::_entities::{inventory_entries, item_tags, taken_items};
+use super::audit_events::{self, Actor};
+use super::tags;
+use crate::dice::DiceExpression;
+use crate::item_files::ItemRow;
 use loco_rs:: {
     model::{ModelError, ModelResult},
     validation,
-    validator::Validate,
+    validator::{Validate, ValidationError},
 };
-use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, DbErr, TransactionTrait, QuerySelect, Paginator, QueryOrder};
-use sea_orm::sea_query::{*, extension::postgres::PgExpr};
-use serde::{Deserialize, Serialize};
+use sea_orm::{entity::prelude::*, ActiveValue, Condition, DatabaseConnection, DbErr, FromQueryResult, IntoActiveModel, JoinType, Order, QueryOrder, QuerySelect, TransactionTrait};
+use sea_orm::sea_query::{IntoCondition, LikeExpr};
+use interface::search::search_key;
+use serde::Deserialize;
 
 #[derive(Debug, Validate, Deserialize)]
 pub struct ModelValidator {
@@ -17,7 +24,34 @@ pub struct ModelValidator {
     #[validate(range(min=1))]
     pub quantity: i32,
     #[validate(range(min=0, max=3))]
-    pub size: i16
+    pub size: i16,
+    #[validate(range(min=1, message="Weight must be at least 1"))]
+    pub weight: i32,
+    #[validate(custom = "validate_rounds")]
+    pub rounds: Option<interface::RoundRange>,
+    #[validate(custom = "crate::dice::validate_expression")]
+    pub rounds_dice: Option<String>
+}
+
+fn validate_rounds(rounds: &interface::RoundRange) -> Result<(), ValidationError> {
+    let message = if rounds.min < 1 {
+        "Items must last at least 1 round"
+    } else if rounds.max < rounds.min {
+        "The most rounds cannot be fewer than the least"
+    } else {
+        return Ok(());
+    };
+    let mut error = ValidationError::new("rounds");
+    error.message = Some(message.into());
+    Err(error)
+}
+
+/// Reads the bounds on rounds of an item, which are either both set or both empty.
+const fn round_range(min_rounds: Option<i16>, max_rounds: Option<i16>) -> Option<interface::RoundRange> {
+    match (min_rounds, max_rounds) {
+        (Some(min), Some(max)) => Some(interface::RoundRange { min, max }),
+        _ => None,
+    }
 }
 
 impl From<&ActiveModel> for ModelValidator {
@@ -25,26 +59,45 @@ impl From<&ActiveModel> for ModelValidator {
         Self {
             name: value.name.as_ref().to_string(),
             quantity: *value.quantity.as_ref(),
-            size: *value.size.as_ref()
+            size: *value.size.as_ref(),
+            weight: *value.weight.as_ref(),
+            rounds: round_range(*value.min_rounds.as_ref(), *value.max_rounds.as_ref()),
+            rounds_dice: value.rounds_dice.as_ref().clone()
         }
     }
 }
 
-impl Into<interface::Item> for Model {
-    fn into(self) -> interface::Item {
-        interface::Item {
-            created_at: self.created_at,
-            updated_at: self.updated_at,
-            name: self.name,
-            description: self.description,
-            id: self.id,
-            size: interface::ItemSize::from_repr(self.size).unwrap(),
-            infinite: self.infinite,
-            quantity: self.quantity
+/// Errors of updating an item the caller may hold an outdated copy of.
+#[derive(Debug)]
+pub enum UpdateError {
+    /// the item changed since the expected version, holds its current version
+    VersionMismatch(i32),
+    Model(ModelError),
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VersionMismatch(version) => write!(f, "the item changed, it is now at version {version}"),
+            Self::Model(err) => err.fmt(f),
         }
     }
 }
 
+impl std::error::Error for UpdateError {}
+
+impl From<ModelError> for UpdateError {
+    fn from(value: ModelError) -> Self {
+        Self::Model(value)
+    }
+}
+
+impl From<DbErr> for UpdateError {
+    fn from(value: DbErr) -> Self {
+        Self::Model(value.into())
+    }
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     // extend activemodel below (keep comment for generators)
@@ -57,6 +110,14 @@ impl ActiveModelBehavior for ActiveModel {
 
             if insert {
                 this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+            } else if let ActiveValue::Set(version) | ActiveValue::Unchanged(version) = this.version {
+                this.version = ActiveValue::Set(version + 1);
+            }
+            if let ActiveValue::Set(name) = &this.name {
+                this.search_name = ActiveValue::Set(search_key(name));
+            }
+            if let ActiveValue::Set(description) = &this.description {
+                this.search_description = ActiveValue::Set(description.as_deref().map(search_key));
             }
             this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
             Ok(this)
@@ -64,110 +125,453 @@ impl ActiveModelBehavior for ActiveModel {
     }
 }
 
+/// How often an item was drawn, per the draws in its history that were not
+/// rerolled.
+#[derive(Clone, Copy, Debug, Default, FromQueryResult)]
+struct DrawStats {
+    item_id: i32,
+    times_drawn: i64,
+    last_drawn_at: Option<DateTime>,
+}
+
+impl DrawStats {
+    /// loads the draw stats of the given items, in a single query
+    async fn for_items<C: ConnectionTrait>(db: &C, ids: Vec<i32>) -> Result<HashMap<i32, Self>, DbErr> {
+        Ok(taken_items::Entity::find()
+            .select_only()
+            .column(taken_items::Column::ItemId)
+            .column_as(taken_items::Column::Id.count(), "times_drawn")
+            .column_as(taken_items::Column::CreatedAt.max(), "last_drawn_at")
+            .filter(taken_items::Column::ItemId.is_in(ids))
+            .filter(taken_items::Column::Rerolled.eq(false))
+            .group_by(taken_items::Column::ItemId)
+            .into_model::<Self>()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|stats| (stats.item_id, stats))
+            .collect())
+    }
+}
+
 impl Model {
-    pub async fn find_by_id(db: &DatabaseConnection, id: i32) -> ModelResult<Self> {
+    /// How many rounds the item lasts once drawn, unless it follows its size.
+    #[must_use]
+    pub const fn rounds(&self) -> Option<interface::RoundRange> {
+        round_range(self.min_rounds, self.max_rounds)
+    }
+
+    /// The dice rolled for the rounds of the item, which win over its `rounds`.
+    #[must_use]
+    pub fn rounds_dice(&self) -> Option<DiceExpression> {
+        self.rounds_dice.as_ref().and_then(|dice| dice.parse().ok())
+    }
+
+    /// converts the item for the API, along with its tags, draw stats and
+    /// the balance of its ledger
+    fn into_interface(self, tags: Vec<tags::Model>, stats: Option<&DrawStats>, balance: Option<i64>) -> interface::Item {
+        let stats = stats.copied().unwrap_or_default();
+        let remaining = (!self.infinite).then(|| i32::try_from(balance.unwrap_or(0).max(0)).unwrap_or(i32::MAX));
+        let rounds = self.rounds();
+        let mut tags: Vec<String> = tags.into_iter().map(|tag| tag.name).collect();
+        tags.sort();
+        interface::Item {
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            name: self.name,
+            description: self.description,
+            id: self.id,
+            bag_id: self.bag_id,
+            size: interface::ItemSize::from_repr(self.size).unwrap(),
+            infinite: self.infinite,
+            quantity: self.quantity,
+            weight: self.weight,
+            tags,
+            rounds,
+            rounds_dice: self.rounds_dice,
+            deleted_at: self.deleted_at,
+            version: self.version,
+            remaining,
+            times_drawn: stats.times_drawn,
+            last_drawn_at: stats.last_drawn_at
+        }
+    }
+
+    pub async fn find_by_id<C: ConnectionTrait>(db: &C, bag_id: i32, id: i32) -> ModelResult<Self> {
         let item = items::Entity::find()
             .filter(items::Column::Id.eq(id))
+            .filter(items::Column::BagId.eq(bag_id))
+            .filter(items::Column::DeletedAt.is_null())
             .one(db)
             .await?;
         item.ok_or_else(|| ModelError::EntityNotFound)
     }
 
-    pub async fn create(db: &DatabaseConnection, create: interface::CreateUpdateItem) -> ModelResult<Self> {
+    pub async fn create(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, create: interface::CreateUpdateItem) -> ModelResult<Self> {
         let txn = db.begin().await?;
-        let item = items::ActiveModel {
-            name: ActiveValue::Set(create.name),
-            description: ActiveValue::Set(create.description),
-            quantity: ActiveValue::Set(create.quantity),
-            size: ActiveValue::Set(create.size as i16),
-            infinite: ActiveValue::Set(create.infinite),
-            ..Default::default()
-        }
-            .insert(&txn)
-            .await?;
-
+        let item = Self::save(&txn, actor, bag_id, None, create).await?;
         txn.commit().await?;
         Ok(item)
     }
 
-    pub async fn update(db: &DatabaseConnection, id: i32, update: interface::CreateUpdateItem) -> ModelResult<Self> {
+    /// Overwrites the item. When `expected_versions` is given the update only
+    /// goes through if the item is still at one of those versions, so
+    /// concurrent edits do not silently overwrite each other.
+    ///
+    /// # Errors
+    ///
+    /// When the item is not in the bag, it is at another version, or DB query error
+    pub async fn update(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32, expected_versions: Option<&[i32]>, update: interface::CreateUpdateItem) -> Result<Self, UpdateError> {
         let txn = db.begin().await?;
 
-        if items::Entity::find()
+        let existing = items::Entity::find()
             .filter(items::Column::Id.eq(id))
+            .filter(items::Column::BagId.eq(bag_id))
+            .filter(items::Column::DeletedAt.is_null())
             .one(&txn)
             .await?
-            .is_none()
-        {
-            return Err(ModelError::EntityNotFound {});
+            .ok_or(ModelError::EntityNotFound)?;
+        if expected_versions.is_some_and(|versions| !versions.contains(&existing.version)) {
+            return Err(UpdateError::VersionMismatch(existing.version));
         }
 
-        let item = items::ActiveModel {
-            name: ActiveValue::Set(update.name),
-            description: ActiveValue::Set(update.description),
-            quantity: ActiveValue::Set(update.quantity),
-            size: ActiveValue::Set(update.size as i16),
-            infinite: ActiveValue::Set(update.infinite),
-            id: ActiveValue::Set(id),
+        match Self::save(&txn, actor, bag_id, Some(existing), update).await {
+            Ok(item) => {
+                txn.commit().await?;
+                Ok(item)
+            }
+            // another update got in between reading the item and writing it
+            Err(ModelError::DbErr(DbErr::RecordNotUpdated)) => {
+                txn.rollback().await?;
+                let current = Self::find_by_id(db, bag_id, id).await?;
+                Err(UpdateError::VersionMismatch(current.version))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Changes the fields given in `patch` and keeps the others. The merged
+    /// item is validated and saved like a full update, against the version
+    /// the patch was applied to.
+    ///
+    /// # Errors
+    ///
+    /// When the item is not in the bag, it is at another version, the merged
+    /// item is invalid, or DB query error
+    pub async fn patch(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32, expected_versions: Option<&[i32]>, patch: interface::PatchItem) -> Result<Self, UpdateError> {
+        let existing = Self::find_by_id(db, bag_id, id).await?;
+        let version = existing.version;
+        if expected_versions.is_some_and(|versions| !versions.contains(&version)) {
+            return Err(UpdateError::VersionMismatch(version));
+        }
+
+        let merged = interface::CreateUpdateItem::from(existing.into_item(db).await?).patched(patch);
+        Self::update(db, actor, bag_id, id, Some(&[version]), merged).await
+    }
+
+    /// inserts `item` into the bag, or writes it over the `existing` item, along with its tags
+    async fn save<C: ConnectionTrait>(db: &C, actor: Actor<'_>, bag_id: i32, existing: Option<Self>, item: interface::CreateUpdateItem) -> ModelResult<Self> {
+        let model = items::ActiveModel {
+            bag_id: ActiveValue::Set(bag_id),
+            name: ActiveValue::Set(item.name),
+            description: ActiveValue::Set(item.description),
+            quantity: ActiveValue::Set(item.quantity),
+            size: ActiveValue::Set(item.size as i16),
+            infinite: ActiveValue::Set(item.infinite),
+            weight: ActiveValue::Set(item.weight),
+            min_rounds: ActiveValue::Set(item.rounds.map(|rounds| rounds.min)),
+            max_rounds: ActiveValue::Set(item.rounds.map(|rounds| rounds.max)),
+            rounds_dice: ActiveValue::Set(item.rounds_dice),
             ..Default::default()
+        };
+        let saved = match &existing {
+            Some(existing) => {
+                let model = items::ActiveModel {
+                    id: ActiveValue::Unchanged(existing.id),
+                    version: ActiveValue::Unchanged(existing.version),
+                    ..model
+                };
+                // a filtered update skips the hooks, which fill in the search
+                // keys and bump the version
+                items::Entity::update(model.before_save(db, false).await?)
+                    .filter(items::Column::Version.eq(existing.version))
+                    .exec(db)
+                    .await?
+            }
+            None => model.insert(db).await?,
+        };
+        item_tags::Model::set_for_item(db, bag_id, saved.id, &item.tags).await?;
+
+        // new items come into stock, and quantity changes move the stock along
+        let added = saved.quantity - existing.as_ref().map_or(0, |existing| existing.quantity);
+        if added != 0 {
+            let (kind, reason) = match &existing {
+                None => (interface::InventoryKind::Add, None),
+                Some(_) if added > 0 => (interface::InventoryKind::Add, Some("quantity changed".to_string())),
+                Some(_) => (interface::InventoryKind::Remove, Some("quantity changed".to_string())),
+            };
+            inventory_entries::Model::record(db, &saved, kind, added, reason, None).await?;
         }
-            .update(&txn)
+
+        let action = if existing.is_some() {
+            interface::AuditAction::Update
+        } else {
+            interface::AuditAction::Create
+        };
+        audit_events::Model::record(db, actor, action, existing.as_ref(), Some(&saved)).await?;
+        Ok(saved)
+    }
+
+    /// Adds the items read from a file to the bag, or updates the items with
+    /// the same name when `upsert` is set, in which case a name may appear
+    /// only once in the file. Every row is checked with [`ModelValidator`] and
+    /// nothing is saved when any row has errors or on a dry run.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn import(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, rows: Vec<ItemRow>, options: &interface::ItemImportOptions) -> ModelResult<interface::ItemImportReport> {
+        let txn = db.begin().await?;
+        let mut report = interface::ItemImportReport {
+            dry_run: options.dry_run,
+            ..Default::default()
+        };
+        // the row each name was first seen on, when upserting
+        let mut names: HashMap<String, usize> = HashMap::new();
+
+        for (index, row) in rows.into_iter().enumerate() {
+            let checked = row
+                .map_err(|error| vec![error])
+                .and_then(|item| Self::check(&item).map(|()| item));
+            let item = match checked {
+                Ok(item) => item,
+                Err(errors) => {
+                    report.errors.push(interface::ImportRowError { row: index + 1, errors });
+                    continue;
+                }
+            };
+            if options.upsert {
+                if let Some(first) = names.get(&item.name) {
+                    report.errors.push(interface::ImportRowError {
+                        row: index + 1,
+                        errors: vec![format!("name: {} is already on row {first}", item.name)],
+                    });
+                    continue;
+                }
+                names.insert(item.name.clone(), index + 1);
+            }
+
+            let existing = if options.upsert {
+                items::Entity::find()
+                    .filter(items::Column::BagId.eq(bag_id))
+                    .filter(items::Column::Name.eq(item.name.as_str()))
+                    .filter(items::Column::DeletedAt.is_null())
+                    .order_by_asc(items::Column::Id)
+                    .one(&txn)
+                    .await?
+            } else {
+                None
+            };
+            if existing.is_some() {
+                report.updated += 1;
+            } else {
+                report.created += 1;
+            }
+            Self::save(&txn, actor, bag_id, existing, item).await?;
+        }
+
+        if report.dry_run || !report.errors.is_empty() {
+            txn.rollback().await?;
+        } else {
+            txn.commit().await?;
+        }
+        Ok(report)
+    }
+
+    /// validates `item` the same way saving it would, with one message per invalid field
+    fn check(item: &interface::CreateUpdateItem) -> Result<(), Vec<String>> {
+        let validator = ModelValidator {
+            name: item.name.clone(),
+            quantity: item.quantity,
+            size: item.size.clone() as i16,
+            weight: item.weight,
+            rounds: item.rounds,
+            rounds_dice: item.rounds_dice.clone(),
+        };
+        validator.validate().map_err(|err| {
+            let mut fields: Vec<String> = validation::into_errors(&err)
+                .into_iter()
+                .map(|(field, errors)| {
+                    let messages: Vec<String> = errors
+                        .into_iter()
+                        .map(|error| error.message.unwrap_or(error.code))
+                        .collect();
+                    format!("{field}: {}", messages.join(", "))
+                })
+                .collect();
+            fields.sort();
+            fields
+        })
+    }
+
+    /// Lists every item in the bag, oldest first, in the shape they are imported in.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn export(db: &DatabaseConnection, bag_id: i32) -> ModelResult<Vec<interface::CreateUpdateItem>> {
+        let items = items::Entity::find()
+            .filter(items::Column::BagId.eq(bag_id))
+            .filter(items::Column::DeletedAt.is_null())
+            .order_by_asc(items::Column::Id)
+            .all(db)
             .await?;
-        txn.commit().await?;
-        Ok(item)
+        Ok(Self::with_tags(db, items).await?.into_iter().map(Into::into).collect())
     }
 
-    pub async fn delete(db: &DatabaseConnection, id: i32) -> ModelResult<()> {
+    /// Archives the item: it is hidden from listings and draws, but its draw
+    /// history is kept and it can be restored until it is purged.
+    ///
+    /// # Errors
+    ///
+    /// When the item is not in the bag or already archived, or DB query error
+    pub async fn delete(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32) -> ModelResult<()> {
         let txn = db.begin().await?;
+        let before = Self::find_by_id(&txn, bag_id, id).await?;
+        let mut item = before.clone().into_active_model();
+        item.deleted_at = ActiveValue::Set(Some(Utc::now().naive_utc()));
+        let after = item.update(&txn).await?;
+        audit_events::Model::record(&txn, actor, interface::AuditAction::Archive, Some(&before), Some(&after)).await?;
+        txn.commit().await?;
+        Ok(())
+    }
 
-        if items::Entity::find()
+    /// Puts an archived item back into the bag.
+    ///
+    /// # Errors
+    ///
+    /// When the item is not in the bag or not archived, or DB query error
+    pub async fn restore(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32) -> ModelResult<Self> {
+        let txn = db.begin().await?;
+        let before = items::Entity::find()
             .filter(items::Column::Id.eq(id))
+            .filter(items::Column::BagId.eq(bag_id))
+            .filter(items::Column::DeletedAt.is_not_null())
             .one(&txn)
             .await?
-            .is_none()
-        {
-            return Err(ModelError::EntityNotFound {});
-        }
+            .ok_or(ModelError::EntityNotFound)?;
+        let mut item = before.clone().into_active_model();
+        item.deleted_at = ActiveValue::Set(None);
+        let after = item.update(&txn).await?;
+        audit_events::Model::record(&txn, actor, interface::AuditAction::Restore, Some(&before), Some(&after)).await?;
+        txn.commit().await?;
+        Ok(after)
+    }
 
-        let item = items::ActiveModel {
-            id: ActiveValue::Set(id),
-            ..Default::default()
-        }
-            .delete(&txn)
+    /// Permanently removes the items of every bag archived before `archived_before`,
+    /// together with their tags, draw history and ledger. Returns how many items were removed.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn purge(db: &DatabaseConnection, actor: Actor<'_>, archived_before: NaiveDateTime) -> ModelResult<u64> {
+        let txn = db.begin().await?;
+        let expired = items::Entity::find()
+            .filter(items::Column::DeletedAt.lt(archived_before))
+            .all(&txn)
+            .await?;
+        let ids: Vec<i32> = expired.iter().map(|item| item.id).collect();
+
+        inventory_entries::Entity::delete_many()
+            .filter(inventory_entries::Column::ItemId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+        taken_items::Entity::delete_many()
+            .filter(taken_items::Column::ItemId.is_in(ids.clone()))
+            .exec(&txn)
             .await?;
+        item_tags::Entity::delete_many()
+            .filter(item_tags::Column::ItemId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+        items::Entity::delete_many()
+            .filter(items::Column::Id.is_in(ids))
+            .exec(&txn)
+            .await?;
+        for item in &expired {
+            audit_events::Model::record(&txn, actor, interface::AuditAction::Purge, Some(item), None).await?;
+        }
 
         txn.commit().await?;
-        Ok(())
+        Ok(expired.len() as u64)
     }
 
-    pub async fn list(db: &DatabaseConnection, filter: Option<interface::ItemFilter>) -> ModelResult<interface::ItemPage> {
+    pub async fn list(db: &DatabaseConnection, bag_id: i32, filter: Option<interface::ItemFilter>) -> ModelResult<interface::ItemPage> {
         let filter = filter.unwrap_or_default();
 
-        let mut query = items::Entity::find()
-            .order_by_desc(items::Column::Id);
-        if let Some(mut name) = filter.name {
-            if !name.contains("%") {
-                name = name + "%";
-            }
-            query = query.filter(Expr::col(items::Column::Name).ilike(name));
+        let mut condition = Condition::all().add(items::Column::BagId.eq(bag_id));
+        condition = if filter.archived {
+            condition.add(items::Column::DeletedAt.is_not_null())
+        } else {
+            condition.add(items::Column::DeletedAt.is_null())
+        };
+        if let Some(name) = filter.name {
+            condition = condition.add(Expr::col(items::Column::SearchName).like(prefix_pattern(&name)));
         }
 
-        if let Some(mut description) = filter.description {
-            if !description.contains("%") {
-                description = description + "%";
-            }
-            query = query.filter(Expr::col(items::Column::Description).ilike(description));
+        if let Some(description) = filter.description {
+            condition = condition.add(Expr::col(items::Column::SearchDescription).like(prefix_pattern(&description)));
+        }
+
+        if let Some(text) = filter.q {
+            let pattern = contains_pattern(&text);
+            condition = condition.add(
+                Condition::any()
+                    .add(Expr::col(items::Column::SearchName).like(pattern.clone()))
+                    .add(Expr::col(items::Column::SearchDescription).like(pattern))
+            );
         }
 
         if let Some(size) = filter.size {
             let item_size = size as i16;
-            query = query.filter(items::Column::Size.eq(item_size));
+            condition = condition.add(items::Column::Size.eq(item_size));
         }
 
         if let Some(infinite) = filter.infinite {
-            query = query.filter(items::Column::Infinite.eq(infinite));
+            condition = condition.add(items::Column::Infinite.eq(infinite));
+        }
+
+        if filter.available {
+            condition = condition.add(inventory_entries::Model::available_condition());
         }
 
+        condition = condition.add(tags::Model::item_condition(&interface::TagFilter {
+            tags: filter.tags,
+            exclude_tags: filter.exclude_tags,
+        }));
+
+        let order = match filter.order {
+            interface::SortOrder::Asc => Order::Asc,
+            interface::SortOrder::Desc => Order::Desc,
+        };
+        let mut query = items::Entity::find().filter(condition.clone());
+        query = match filter.sort {
+            interface::ItemSort::CreatedAt => query.order_by(items::Column::CreatedAt, order.clone()),
+            interface::ItemSort::Name => query.order_by(items::Column::Name, order.clone()),
+            interface::ItemSort::Quantity => query.order_by(items::Column::Quantity, order.clone()),
+            interface::ItemSort::TimesDrawn => query
+                .join(
+                    JoinType::LeftJoin,
+                    items::Relation::TakenItems.def().on_condition(|_, taken| {
+                        Expr::col((taken, taken_items::Column::Rerolled)).eq(false).into_condition()
+                    }),
+                )
+                .group_by(items::Column::Id)
+                .order_by(taken_items::Column::Id.into_expr().count(), order.clone()),
+        };
+        // keeps pages stable between items that sort the same
+        query = query.order_by(items::Column::Id, order);
+
         let page_size = if filter.page_size > 0 {
             filter.page_size
         } else {
@@ -176,21 +580,101 @@ impl Model {
 
         let paginator = query.paginate(db, page_size);
         let items_and_pages = paginator.num_items_and_pages().await?;
-        let items = paginator
-            .fetch_page(filter.page_num)
-            .await?
-            .into_iter()
-            .map(Into::into)
-            .collect();
+        let items = Self::with_tags(db, paginator.fetch_page(filter.page_num).await?).await?;
 
         Ok(interface::ItemPage {
-            items: items,
+            items,
             page_num: filter.page_num,
-            page_size: page_size,
+            page_size,
             total_pages: items_and_pages.number_of_pages,
-            total_results: items_and_pages.number_of_items
+            total_results: items_and_pages.number_of_items,
+            facets: Self::facets(db, condition).await?
         })
     }
+
+    /// loads the tags, draw stats and stock of `items` and converts them for the API
+    pub async fn with_tags<C: ConnectionTrait>(db: &C, items: Vec<Self>) -> ModelResult<Vec<interface::Item>> {
+        let tags = items.load_many_to_many(tags::Entity, item_tags::Entity, db).await?;
+        let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+        let stats = DrawStats::for_items(db, ids.clone()).await?;
+        let balances = inventory_entries::Model::balances(db, ids).await?;
+        Ok(items
+            .into_iter()
+            .zip(tags)
+            .map(|(item, tags)| {
+                let stats = stats.get(&item.id);
+                let balance = balances.get(&item.id).copied();
+                item.into_interface(tags, stats, balance)
+            })
+            .collect())
+    }
+
+    /// loads the tags, draw stats and stock of the item and converts it for the API
+    pub async fn into_item<C: ConnectionTrait>(self, db: &C) -> ModelResult<interface::Item> {
+        let tags = self.find_related(tags::Entity).all(db).await?;
+        let stats = DrawStats::for_items(db, vec![self.id]).await?;
+        let stats = stats.get(&self.id);
+        let balance = inventory_entries::Model::balance(db, self.id).await?;
+        Ok(self.into_interface(tags, stats, Some(balance)))
+    }
+
+    /// counts the items matching `condition` per size and per infinite flag
+    async fn facets(db: &DatabaseConnection, condition: Condition) -> ModelResult<interface::ItemFacets> {
+        let sizes = items::Entity::find()
+            .select_only()
+            .column(items::Column::Size)
+            .column_as(items::Column::Id.count(), "count")
+            .filter(condition.clone())
+            .group_by(items::Column::Size)
+            .order_by_asc(items::Column::Size)
+            .into_tuple::<(i16, i64)>()
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(size, count)| Some(interface::SizeFacet {
+                size: interface::ItemSize::from_repr(size)?,
+                count: u64::try_from(count).unwrap_or_default(),
+            }))
+            .collect();
+
+        let infinite = items::Entity::find()
+            .select_only()
+            .column(items::Column::Infinite)
+            .column_as(items::Column::Id.count(), "count")
+            .filter(condition)
+            .group_by(items::Column::Infinite)
+            .order_by_asc(items::Column::Infinite)
+            .into_tuple::<(bool, i64)>()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|(infinite, count)| interface::InfiniteFacet {
+                infinite,
+                count: u64::try_from(count).unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(interface::ItemFacets { sizes, infinite })
+    }
+}
+
+/// Matches search columns starting with `text`; `%` and `_` in `text` act as
+/// wildcards, as they always have for the name and description filters.
+fn prefix_pattern(text: &str) -> LikeExpr {
+    let mut pattern = search_key(text);
+    if !pattern.contains('%') {
+        pattern += "%";
+    }
+    LikeExpr::new(pattern)
+}
+
+/// Matches search columns containing `text` anywhere.
+fn contains_pattern(text: &str) -> LikeExpr {
+    let escaped = search_key(text)
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    LikeExpr::new(format!("%{escaped}%")).escape('\\')
 }
 
 impl ActiveModel {
@@ -2,5 +2,14 @@ pub mod _entities;
 //pub mod notes;
 pub mod users;
 
+pub mod audit_events;
+pub mod bag_invites;
+pub mod bag_members;
+pub mod bags;
+pub mod draw_transitions;
+pub mod game_rounds;
+pub mod inventory_entries;
+pub mod tags;
 pub mod taken_items;
+pub mod item_tags;
 pub mod items;
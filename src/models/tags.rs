@@ -0,0 +1,216 @@
+use chrono::Utc;
+use loco_rs::{
+    model::{ModelError, ModelResult},
+    validation,
+    validator::Validate,
+};
+use sea_orm::{
+    entity::prelude::*, sea_query::Query, ActiveValue, Condition, ConnectionTrait, DatabaseConnection, DbErr,
+    QueryOrder, SqlErr,
+};
+use serde::Deserialize;
+
+pub use super::_entities::tags::{self, ActiveModel, Entity, Model};
+use super::_entities::{item_tags, items};
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct ModelValidator {
+    #[validate(length(min = 1, message = "Name must be at least 1 character long"))]
+    pub name: String,
+}
+
+impl From<&ActiveModel> for ModelValidator {
+    fn from(value: &ActiveModel) -> Self {
+        Self {
+            name: value.name.as_ref().to_string(),
+        }
+    }
+}
+
+impl From<Model> for interface::Tag {
+    fn from(value: Model) -> Self {
+        Self {
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            id: value.id,
+            bag_id: value.bag_id,
+            name: value.name,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        let mut this = self;
+
+        if insert {
+            this.created_at = ActiveValue::Set(Utc::now().naive_utc());
+        }
+        this.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+        Ok(this)
+    }
+}
+
+/// Maps the unique index on tag names in a bag to a model error
+fn map_duplicate(err: DbErr) -> ModelError {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => ModelError::EntityAlreadyExists,
+        _ => err.into(),
+    }
+}
+
+impl Model {
+    /// finds a tag of the bag by its id
+    ///
+    /// # Errors
+    ///
+    /// When the tag does not exist in the bag or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, bag_id: i32, id: i32) -> ModelResult<Self> {
+        let tag = tags::Entity::find_by_id(id)
+            .filter(tags::Column::BagId.eq(bag_id))
+            .one(db)
+            .await?;
+        tag.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// lists the tags of the bag by name
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn list_for_bag(db: &DatabaseConnection, bag_id: i32) -> ModelResult<Vec<Self>> {
+        Ok(tags::Entity::find()
+            .filter(tags::Column::BagId.eq(bag_id))
+            .order_by_asc(tags::Column::Name)
+            .all(db)
+            .await?)
+    }
+
+    /// creates a tag in the bag
+    ///
+    /// # Errors
+    ///
+    /// When the bag already has a tag with this name, the tag is not valid or DB query error
+    pub async fn create(db: &DatabaseConnection, bag_id: i32, create: interface::CreateUpdateTag) -> ModelResult<Self> {
+        tags::ActiveModel {
+            bag_id: ActiveValue::Set(bag_id),
+            name: ActiveValue::Set(create.name.trim().to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .map_err(map_duplicate)
+    }
+
+    /// renames a tag of the bag
+    ///
+    /// # Errors
+    ///
+    /// When the tag does not exist in the bag, the new name is taken or DB query error
+    pub async fn update(
+        db: &DatabaseConnection,
+        bag_id: i32,
+        id: i32,
+        update: interface::CreateUpdateTag,
+    ) -> ModelResult<Self> {
+        Self::find_by_id(db, bag_id, id).await?;
+        tags::ActiveModel {
+            id: ActiveValue::Unchanged(id),
+            name: ActiveValue::Set(update.name.trim().to_string()),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+        .map_err(map_duplicate)
+    }
+
+    /// deletes a tag of the bag, removing it from every item
+    ///
+    /// # Errors
+    ///
+    /// When the tag does not exist in the bag or DB query error
+    pub async fn delete(db: &DatabaseConnection, bag_id: i32, id: i32) -> ModelResult<()> {
+        Self::find_by_id(db, bag_id, id).await?;
+        item_tags::Entity::delete_many()
+            .filter(item_tags::Column::TagId.eq(id))
+            .exec(db)
+            .await?;
+        tags::Entity::delete_by_id(id).exec(db).await?;
+        Ok(())
+    }
+
+    /// finds the tags of the bag with the given names, creating the missing ones
+    ///
+    /// # Errors
+    ///
+    /// When a name is not valid or DB query error
+    pub async fn find_or_create<C: ConnectionTrait>(db: &C, bag_id: i32, names: &[String]) -> ModelResult<Vec<Self>> {
+        let mut names: Vec<String> = names.iter().map(|name| name.trim().to_string()).collect();
+        names.sort();
+        names.dedup();
+
+        let mut tags = tags::Entity::find()
+            .filter(tags::Column::BagId.eq(bag_id))
+            .filter(tags::Column::Name.is_in(names.clone()))
+            .all(db)
+            .await?;
+        for name in names {
+            if !tags.iter().any(|tag| tag.name == name) {
+                let tag = tags::ActiveModel {
+                    bag_id: ActiveValue::Set(bag_id),
+                    name: ActiveValue::Set(name),
+                    ..Default::default()
+                }
+                .insert(db)
+                .await?;
+                tags.push(tag);
+            }
+        }
+        Ok(tags)
+    }
+
+    /// Condition on items matching the tag filter
+    #[must_use]
+    pub fn item_condition(filter: &interface::TagFilter) -> Condition {
+        let tagged_with = |names: &[String]| {
+            Query::select()
+                .column((item_tags::Entity, item_tags::Column::ItemId))
+                .from(item_tags::Entity)
+                .inner_join(
+                    tags::Entity,
+                    Expr::col((tags::Entity, tags::Column::Id)).equals((item_tags::Entity, item_tags::Column::TagId)),
+                )
+                .and_where(Expr::col((tags::Entity, tags::Column::Name)).is_in(names.to_vec()))
+                .to_owned()
+        };
+
+        let mut condition = Condition::all();
+        if !filter.tags.is_empty() {
+            condition = condition.add(items::Column::Id.in_subquery(tagged_with(&filter.tags)));
+        }
+        if !filter.exclude_tags.is_empty() {
+            condition = condition.add(items::Column::Id.not_in_subquery(tagged_with(&filter.exclude_tags)));
+        }
+        condition
+    }
+}
+
+impl ActiveModel {
+    /// Validate tag schema
+    ///
+    /// # Errors
+    ///
+    /// when the active model is not valid
+    pub fn validate(&self) -> Result<(), DbErr> {
+        let validator: ModelValidator = self.into();
+        validator
+            .validate()
+            .map_err(|e| validation::into_db_error(&e))
+    }
+}
@@ -1,13 +1,86 @@
 use chrono::Utc;
-use sea_orm::{ActiveValue, JoinType, QuerySelect, TransactionTrait};
+use std::collections::HashMap;
+use std::fmt;
+use sea_orm::{ActiveValue, Condition, IntoActiveModel, JoinType, QueryOrder, QuerySelect, SqlErr, TransactionTrait};
 use sea_orm::entity::prelude::*;
-use loco_rs:: {
-    model::{ModelError, ModelResult},
-};
-use sea_orm::sea_query::{Alias, Query};
-use interface::TakenItem;
+use loco_rs::model::{ModelError, ModelResult};
+use interface::{TakenItem, TakenStatus};
+use crate::dice;
+use crate::draw::{self, Candidate, DrawStrategy};
 pub use super::_entities::taken_items::{self, Entity, ActiveModel, Model};
-use super::_entities::items;
+use super::_entities::{bags, inventory_entries, items, users};
+use super::audit_events::{self, Actor};
+use super::draw_transitions;
+use super::game_rounds;
+use super::tags;
+
+/// Errors of drawing an item from a bag.
+#[derive(Debug)]
+pub enum DrawError {
+    /// no item of the bag has any quantity left
+    BagExhausted(i32),
+    /// the bag has no rerolls left in this game, holds its budget
+    RerollBudgetSpent(i16),
+    /// concurrent draws kept taking the free slots of the hand of the bag
+    Concurrent(i32),
+    Model(ModelError),
+}
+
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BagExhausted(bag_id) => write!(f, "no items left in bag {bag_id}"),
+            Self::RerollBudgetSpent(budget) => write!(f, "all {budget} rerolls of this game were used"),
+            Self::Concurrent(bag_id) => write!(f, "bag {bag_id} was drawn from too often at the same time"),
+            Self::Model(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DrawError {}
+
+impl From<ModelError> for DrawError {
+    fn from(value: ModelError) -> Self {
+        Self::Model(value)
+    }
+}
+
+impl From<DbErr> for DrawError {
+    fn from(value: DbErr) -> Self {
+        Self::Model(value.into())
+    }
+}
+
+/// Errors of changing the rounds left on a draw.
+#[derive(Debug)]
+pub enum RoundsError {
+    /// the amount to change the rounds by makes no sense
+    InvalidAmount(i16),
+    Model(ModelError),
+}
+
+impl fmt::Display for RoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAmount(delta) => write!(f, "cannot change the rounds of a draw by {delta}"),
+            Self::Model(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RoundsError {}
+
+impl From<ModelError> for RoundsError {
+    fn from(value: ModelError) -> Self {
+        Self::Model(value)
+    }
+}
+
+impl From<DbErr> for RoundsError {
+    fn from(value: DbErr) -> Self {
+        Self::Model(value.into())
+    }
+}
 
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
@@ -26,109 +99,553 @@ impl ActiveModelBehavior for ActiveModel {
     }
 }
 
-impl Into<interface::TakenItem> for Model {
-    fn into(self) -> TakenItem {
-        TakenItem {
-            created_at: self.created_at,
-            updated_at: self.updated_at,
-            id: self.id,
-            item_id: self.item_id,
-            rounds_left: self.rounds_left,
-            done: self.done,
-            rounds_total: self.rounds_total
+impl From<Model> for TakenItem {
+    fn from(value: Model) -> Self {
+        Self {
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            id: value.id,
+            bag_id: value.bag_id,
+            item_id: value.item_id,
+            rounds_left: value.rounds_left,
+            status: value.status(),
+            rounds_total: value.rounds_total,
+            // rolls are only ever written from a `DiceRoll`
+            rounds_roll: value.rounds_roll.and_then(|roll| serde_json::from_value(roll).ok())
         }
     }
 }
 
+/// How many times a draw is tried again after concurrent draws took the slot
+/// it picked.
+const DRAW_ATTEMPTS: usize = 5;
+
 impl Model {
-    pub async fn get_current(db: &DatabaseConnection) -> ModelResult<Option<interface::TakenItem>> {
-        Ok(taken_items::Entity::find()
+    #[must_use]
+    pub const fn status(&self) -> TakenStatus {
+        if self.rerolled {
+            TakenStatus::Rerolled
+        } else if self.done {
+            TakenStatus::Done
+        } else if self.paused {
+            TakenStatus::Paused
+        } else {
+            TakenStatus::Active
+        }
+    }
+
+    /// The newest draw in the hand of the bag, paused or not.
+    pub async fn get_current<C: ConnectionTrait>(db: &C, bag_id: i32) -> ModelResult<Option<interface::TakenItem>> {
+        Ok(Self::find_current(db, bag_id, None).await?.map(Into::into))
+    }
+
+    async fn find_current<C: ConnectionTrait>(db: &C, bag_id: i32, status: Option<TakenStatus>) -> Result<Option<Self>, DbErr> {
+        Self::with_status(taken_items::Entity::find(), status)
+            .filter(taken_items::Column::BagId.eq(bag_id))
+            .order_by_desc(taken_items::Column::Id)
+            .one(db)
+            .await
+    }
+
+    /// Narrows the query to draws in the hand, to active or paused ones only
+    /// when `status` says which.
+    fn with_status(query: Select<Entity>, status: Option<TakenStatus>) -> Select<Entity> {
+        let query = query.filter(taken_items::Column::Done.eq(false));
+        match status {
+            Some(status) => query.filter(taken_items::Column::Paused.eq(status == TakenStatus::Paused)),
+            None => query,
+        }
+    }
+
+    /// Lists the active and paused draws of the bag, in the order of their
+    /// slots in the hand.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn get_hand<C: ConnectionTrait>(db: &C, bag_id: i32) -> ModelResult<Vec<interface::TakenItem>> {
+        Ok(Self::find_hand(db, bag_id).await?.into_iter().map(Into::into).collect())
+    }
+
+    async fn find_hand<C: ConnectionTrait>(db: &C, bag_id: i32) -> Result<Vec<Self>, DbErr> {
+        taken_items::Entity::find()
+            .filter(taken_items::Column::BagId.eq(bag_id))
             .filter(taken_items::Column::Done.eq(false))
+            .order_by_asc(taken_items::Column::HandSlot)
+            .order_by_asc(taken_items::Column::Id)
+            .all(db)
+            .await
+    }
+
+    async fn find_in_hand<C: ConnectionTrait>(db: &C, bag_id: i32, id: i32, status: Option<TakenStatus>) -> ModelResult<Self> {
+        Self::with_status(taken_items::Entity::find(), status)
+            .filter(taken_items::Column::Id.eq(id))
+            .filter(taken_items::Column::BagId.eq(bag_id))
             .one(db)
             .await?
-            .map(Into::into))
-    }
-
-    pub async fn decrement_rounds(db: &DatabaseConnection) -> ModelResult<Option<interface::TakenItem>> {
-        let current_item = Model::get_current(db).await?;
-        if let Some(itm) = current_item {
-            let new_round_count = itm.rounds_left - 1;
-            let done = new_round_count <= 0;
-            let model = ActiveModel {
-                id: ActiveValue::Set(itm.id),
-                rounds_left: ActiveValue::Set(new_round_count),
-                done: ActiveValue::Set(done),
-                ..Default::default()
-            }
-                .update(db)
-                .await?;
-            Ok(Some(model.into()))
-        } else {
-            Ok(current_item)
+            .ok_or(ModelError::EntityNotFound)
+    }
+
+    /// Takes a round off the draw, which is done once it has none left.
+    async fn decrement<C: ConnectionTrait>(db: &C, actor: Actor<'_>, before: &Self) -> ModelResult<Self> {
+        let new_round_count = before.rounds_left - 1;
+        let mut itm = before.clone().into_active_model();
+        itm.rounds_left = ActiveValue::Set(new_round_count);
+        itm.done = ActiveValue::Set(new_round_count <= 0);
+        let after = itm.update(db).await?;
+        audit_events::Model::record(db, actor, interface::AuditAction::Decrement, Some(before), Some(&after)).await?;
+        Ok(after)
+    }
+
+    /// Takes a round off the active draw with the given id.
+    ///
+    /// # Errors
+    ///
+    /// When the draw is not active in the bag, or DB query error
+    pub async fn decrement_draw(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32, history_depth: u64) -> ModelResult<interface::TakenItem> {
+        let txn = db.begin().await?;
+        let before = Self::find_in_hand(&txn, bag_id, id, Some(TakenStatus::Active)).await?;
+        let after = Self::decrement(&txn, actor, &before).await?;
+        draw_transitions::Model::record(&txn, bag_id, interface::AuditAction::Decrement, &[(before, after.clone())], history_depth).await?;
+        txn.commit().await?;
+        Ok(after.into())
+    }
+
+    /// Marks the active or paused draw with the given id as done.
+    ///
+    /// # Errors
+    ///
+    /// When the draw is not in the hand of the bag, or DB query error
+    pub async fn mark_draw_done(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32, history_depth: u64) -> ModelResult<interface::TakenItem> {
+        let txn = db.begin().await?;
+        let before = Self::find_in_hand(&txn, bag_id, id, None).await?;
+        let mut itm = before.clone().into_active_model();
+        itm.done = ActiveValue::Set(true);
+        let after = itm.update(&txn).await?;
+        audit_events::Model::record(&txn, actor, interface::AuditAction::Done, Some(&before), Some(&after)).await?;
+        draw_transitions::Model::record(&txn, bag_id, interface::AuditAction::Done, &[(before, after.clone())], history_depth).await?;
+        txn.commit().await?;
+        Ok(after.into())
+    }
+
+    /// Adds rounds to the active or paused draw with the given id, or takes
+    /// them off when `delta` is negative. A draw left without rounds is done.
+    ///
+    /// # Errors
+    ///
+    /// When the change is zero, the draw is not in the hand of the bag, or DB
+    /// query error
+    pub async fn adjust_rounds(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32, adjust: interface::AdjustRounds, history_depth: u64) -> Result<interface::TakenItem, RoundsError> {
+        if adjust.delta == 0 {
+            return Err(RoundsError::InvalidAmount(adjust.delta));
         }
+        let txn = db.begin().await?;
+        let before = Self::find_in_hand(&txn, bag_id, id, None).await?;
+        let new_round_count = before.rounds_left.saturating_add(adjust.delta).max(0);
+        let mut itm = before.clone().into_active_model();
+        itm.rounds_left = ActiveValue::Set(new_round_count);
+        itm.done = ActiveValue::Set(new_round_count == 0);
+        let after = itm.update(&txn).await?;
+        audit_events::Model::record(&txn, actor, interface::AuditAction::Adjust, Some(&before), Some(&after)).await?;
+        draw_transitions::Model::record(&txn, bag_id, interface::AuditAction::Adjust, &[(before, after.clone())], history_depth).await?;
+        txn.commit().await?;
+        Ok(after.into())
     }
 
-    pub async fn mark_done(db: &DatabaseConnection) -> ModelResult<()> {
-        let current_item = Model::get_current(db).await?;
-        if let Some(itm) = current_item {
-            let model = ActiveModel {
-                id: ActiveValue::Set(itm.id),
-                done: ActiveValue::Set(true),
-                ..Default::default()
-            }
-                .update(db)
-                .await?;
-            Ok(())
+    /// Pauses the active draw with the given id, which keeps its slot in the
+    /// hand but loses no rounds until it is resumed.
+    ///
+    /// # Errors
+    ///
+    /// When the draw is not active in the bag, or DB query error
+    pub async fn pause(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32, history_depth: u64) -> ModelResult<interface::TakenItem> {
+        Self::set_paused(db, actor, bag_id, id, true, history_depth).await
+    }
+
+    /// Resumes the paused draw with the given id.
+    ///
+    /// # Errors
+    ///
+    /// When the draw is not paused in the bag, or DB query error
+    pub async fn resume(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32, history_depth: u64) -> ModelResult<interface::TakenItem> {
+        Self::set_paused(db, actor, bag_id, id, false, history_depth).await
+    }
+
+    async fn set_paused(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, id: i32, paused: bool, history_depth: u64) -> ModelResult<interface::TakenItem> {
+        let (status, action) = if paused {
+            (TakenStatus::Active, interface::AuditAction::Pause)
         } else {
-            Ok(())
+            (TakenStatus::Paused, interface::AuditAction::Resume)
+        };
+        let txn = db.begin().await?;
+        let before = Self::find_in_hand(&txn, bag_id, id, Some(status)).await?;
+        let mut itm = before.clone().into_active_model();
+        itm.paused = ActiveValue::Set(paused);
+        let after = itm.update(&txn).await?;
+        audit_events::Model::record(&txn, actor, action, Some(&before), Some(&after)).await?;
+        draw_transitions::Model::record(&txn, bag_id, action, &[(before, after.clone())], history_depth).await?;
+        txn.commit().await?;
+        Ok(after.into())
+    }
+
+    /// Takes a round off every active draw of the bag on the given
+    /// connection, as part of advancing the game round. Paused draws sit the
+    /// round out, and the whole hand is undone at once.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn advance_hand<C: ConnectionTrait>(db: &C, actor: Actor<'_>, bag_id: i32, history_depth: u64) -> ModelResult<Vec<Self>> {
+        let mut changed = vec![];
+        let active = Self::find_hand(db, bag_id).await?.into_iter().filter(|taken| !taken.paused);
+        for before in active {
+            let after = Self::decrement(db, actor, &before).await?;
+            changed.push((before, after));
         }
+        draw_transitions::Model::record(db, bag_id, interface::AuditAction::Advance, &changed, history_depth).await?;
+        Ok(changed.into_iter().map(|(_, after)| after).collect())
+    }
+
+    /// Takes a round off the newest active draw of the bag, if any, passing
+    /// over paused ones.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn decrement_rounds(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, history_depth: u64) -> ModelResult<Option<interface::TakenItem>> {
+        let Some(current) = Self::find_current(db, bag_id, Some(TakenStatus::Active)).await? else {
+            return Ok(None);
+        };
+        Self::decrement_draw(db, actor, bag_id, current.id, history_depth).await.map(Some)
+    }
+
+    /// Marks the newest draw in the hand of the bag as done, if any.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn mark_done(db: &DatabaseConnection, actor: Actor<'_>, bag_id: i32, history_depth: u64) -> ModelResult<()> {
+        let Some(current) = Self::find_current(db, bag_id, None).await? else {
+            return Ok(());
+        };
+        Self::mark_draw_done(db, actor, bag_id, current.id, history_depth).await?;
+        Ok(())
+    }
+
+    /// Lists the items of the bag matching the tag filter that have stock
+    /// left in their ledger, leaving out archived ones, together with how
+    /// many times each one was taken since the bag was last restocked.
+    /// Rerolled draws do not count as taken.
+    async fn available_items<C: ConnectionTrait>(db: &C, bag_id: i32, tags: &interface::TagFilter) -> Result<Vec<Candidate>, DbErr> {
+        let times_drawn = Expr::expr(taken_items::Column::Id.into_expr().count()).if_null(0);
+        items::Entity::find()
+            .select_only()
+            .column_as(items::Column::Id, "item_id")
+            .column(items::Column::Weight)
+            .column_as(times_drawn.clone(), "times_drawn")
+            .filter(items::Column::BagId.eq(bag_id))
+            .filter(items::Column::DeletedAt.is_null())
+            .filter(tags::Model::item_condition(tags))
+            .filter(inventory_entries::Model::available_condition())
+            .join(
+                JoinType::LeftJoin,
+                items::Relation::TakenItems.def().on_condition(|_, taken| {
+                    Condition::all()
+                        .add(Expr::col((taken.clone(), taken_items::Column::Restocked)).eq(false))
+                        .add(Expr::col((taken, taken_items::Column::Rerolled)).eq(false))
+                }),
+            )
+            .group_by(items::Column::Id)
+            .into_model::<Candidate>()
+            .all(db)
+            .await
+    }
+
+    /// Puts every finished draw of the bag back and refills the stock of its
+    /// items, so they can be drawn again.
+    async fn restock<C: ConnectionTrait>(db: &C, bag_id: i32) -> Result<(), DbErr> {
+        inventory_entries::Model::restock_bag(db, bag_id).await?;
+        taken_items::Entity::update_many()
+            .col_expr(taken_items::Column::Restocked, Expr::value(true))
+            .filter(taken_items::Column::BagId.eq(bag_id))
+            .filter(taken_items::Column::Done.eq(true))
+            .exec(db)
+            .await?;
+        Ok(())
     }
 
-    pub async fn get_random(db: &DatabaseConnection) -> ModelResult<interface::TakenItem> {
-        let existing = Model::get_current(db).await?;
-        if let Some(ext) = existing {
-            tracing::info!("Current item is {:?}", ext);
-            Ok(ext)
+    /// Lists past and current draws of the bag, newest first, with the drawn
+    /// item and the user who drew it.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn history(db: &DatabaseConnection, bag_id: i32, filter: Option<interface::TakenItemHistoryFilter>) -> ModelResult<interface::TakenItemHistory> {
+        let filter = filter.unwrap_or_default();
+
+        let mut query = taken_items::Entity::find()
+            .filter(taken_items::Column::BagId.eq(bag_id))
+            .order_by_desc(taken_items::Column::CreatedAt)
+            .order_by_desc(taken_items::Column::Id);
+        if let Some(item_id) = filter.item_id {
+            query = query.filter(taken_items::Column::ItemId.eq(item_id));
+        }
+        if let Some(from) = filter.from {
+            query = query.filter(taken_items::Column::CreatedAt.gte(from));
+        }
+        if let Some(to) = filter.to {
+            query = query.filter(taken_items::Column::CreatedAt.lt(to));
+        }
+
+        let page_size = if filter.page_size > 0 {
+            filter.page_size
         } else {
-            let txn = db.begin().await?;
-            let item_uses = items::Entity::find()
-                .left_join(taken_items::Entity)
-                .group_by(items::Column::Id)
-                .to_owned()
-                .having(
-                    Expr::expr(
-                        Expr::case(
-                            items::Column::Infinite.eq(false),
-                            items::Column::Quantity.into_expr().sub(
-                                Expr::expr(taken_items::Column::Id.into_expr().count()).if_null(0)
-                            )
-                        ).finally(1)
-                    ).gte(1)
-                );
-                //.column(items::Column::Id);
-            let item_count = item_uses.clone().count(&txn).await?;
-            tracing::info!("Item count is {}", item_count);
-            let item_offset = rand::random::<u64>().clamp(0, item_count - 1);
-            tracing::info!("Selecting offset {}", item_offset);
-            //let item_id = item_uses.offset(item_offset).into_tuple().one(&txn)
-            //    .await?
-            //    .expect(&format!("Item with offset {} returned None", item_offset));
-            let item = item_uses.offset(item_offset).one(&txn)
-                .await?
-                .expect(&format!("Item with offset {} returned None", item_offset));
-            let total_rounds = rand::random::<i16>().clamp(1, 6);
-            let model = ActiveModel {
-                item_id: ActiveValue::Set(item.id),
-                rounds_left: ActiveValue::Set(total_rounds),
-                rounds_total: ActiveValue::Set(total_rounds),
-                done: ActiveValue::Set(false),
-                ..Default::default()
+            50
+        };
+
+        let paginator = query.find_also_related(items::Entity).paginate(db, page_size);
+        let items_and_pages = paginator.num_items_and_pages().await?;
+        let page = paginator.fetch_page(filter.page_num).await?;
+
+        let user_ids: Vec<i32> = page.iter().filter_map(|(taken, _)| taken.drawn_by).collect();
+        let drawers: HashMap<i32, users::Model> = users::Entity::find()
+            .filter(users::Column::Id.is_in(user_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+
+        let (taken, drawn_items): (Vec<Model>, Vec<Option<items::Model>>) = page.into_iter().unzip();
+        let drawn_items = drawn_items
+            .into_iter()
+            .map(|item| item.ok_or(ModelError::EntityNotFound))
+            .collect::<ModelResult<Vec<_>>>()?;
+        let drawn_items = items::Model::with_tags(db, drawn_items).await?;
+
+        let taken_history = taken
+            .into_iter()
+            .zip(drawn_items)
+            .map(|(taken, item)| {
+                let drawn_by = taken
+                    .drawn_by
+                    .and_then(|id| drawers.get(&id))
+                    .map(|user| interface::DrawnBy {
+                        user_pid: user.pid.to_string(),
+                        name: user.name.clone(),
+                    });
+                interface::TakenItemHistoryEntry {
+                    taken: taken.into(),
+                    item,
+                    drawn_by,
+                }
+            })
+            .collect();
+
+        Ok(interface::TakenItemHistory {
+            taken_history,
+            page_num: filter.page_num,
+            page_size,
+            total_pages: items_and_pages.number_of_pages,
+            total_results: items_and_pages.number_of_items
+        })
+    }
+
+    /// Draws an item matching the tag filter from the bag using the given
+    /// strategy into a free slot of its hand of `hand_size` active draws.
+    /// When the hand is full, its newest draw is returned as is. A hand never
+    /// holds more draws than it has slots, nor an item drawn more often than
+    /// it is stocked, even under concurrent requests.
+    ///
+    /// When nothing is left in the bag it is restocked first if the bag asks
+    /// for it, otherwise the draw fails with [`DrawError::BagExhausted`].
+    ///
+    /// The draw lasts a number of rounds rolled with the dice of the item,
+    /// within its rounds, with the dice of the bag, or within the rounds of
+    /// its size in `rounds`, whichever the item or bag sets first. Dice rolls
+    /// are kept on the draw.
+    ///
+    /// # Errors
+    ///
+    /// When the bag has no available items, concurrent draws keep taking the
+    /// free slots of the hand, or DB query error
+    pub async fn get_random(db: &DatabaseConnection, bag: &bags::Model, actor: Actor<'_>, strategy: &dyn DrawStrategy, rounds: &draw::RoundsBySize, hand_size: i16, tags: &interface::TagFilter) -> Result<interface::TakenItem, DrawError> {
+        for _ in 0..DRAW_ATTEMPTS {
+            if let Some(taken) = Self::draw_into_hand(db, bag, actor, strategy, rounds, hand_size.max(1), tags).await? {
+                return Ok(taken);
             }
-                .insert(&txn)
+            tracing::info!("Concurrent draw in bag {}, drawing again", bag.id);
+        }
+        Err(DrawError::Concurrent(bag.id))
+    }
+
+    /// Draws into the first free slot of the hand, or returns the newest draw
+    /// of a full hand. Returns `None` when a concurrent draw took the slot.
+    async fn draw_into_hand(db: &DatabaseConnection, bag: &bags::Model, actor: Actor<'_>, strategy: &dyn DrawStrategy, rounds: &draw::RoundsBySize, hand_size: i16, tags: &interface::TagFilter) -> Result<Option<interface::TakenItem>, DrawError> {
+        let txn = db.begin().await?;
+        let hand = Self::find_hand(&txn, bag.id).await?;
+        let Some(slot) = (0..hand_size).find(|slot| hand.iter().all(|taken| taken.hand_slot != Some(*slot))) else {
+            tracing::info!("Hand of bag {} is full", bag.id);
+            return Ok(hand.into_iter().max_by_key(|taken| taken.id).map(Into::into));
+        };
+
+        match Self::draw_in_slot(&txn, bag, actor, strategy, rounds, slot, tags).await {
+            Ok(model) => {
+                txn.commit().await?;
+                Ok(Some(model.into()))
+            }
+            // A concurrent request drew into the same slot first and the
+            // unique index on active slots rejected ours.
+            Err(DrawError::Model(ModelError::DbErr(err))) if matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+                txn.rollback().await?;
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Draws an item into the given slot of the hand on the given
+    /// connection, restocking the bag first when it asks for it.
+    async fn draw_in_slot<C: ConnectionTrait>(db: &C, bag: &bags::Model, actor: Actor<'_>, strategy: &dyn DrawStrategy, rounds: &draw::RoundsBySize, slot: i16, tags: &interface::TagFilter) -> Result<Self, DrawError> {
+        let mut candidates = Self::available_items(db, bag.id, tags).await?;
+        if candidates.is_empty() && bag.auto_restock {
+            tracing::info!("Restocking bag {}", bag.id);
+            Self::restock(db, bag.id).await?;
+            audit_events::Model::record(db, actor, interface::AuditAction::Restock, None, Some(bag)).await?;
+            candidates = Self::available_items(db, bag.id, tags).await?;
+        }
+        tracing::info!("Item count is {}", candidates.len());
+        let drawn = loop {
+            // the thread local rng is not Send, so it must not live across an await
+            let index = strategy
+                .choose(&candidates, &mut rand::thread_rng())
+                .ok_or(DrawError::BagExhausted(bag.id))?;
+            tracing::info!("Selected item {}", candidates[index].item_id);
+            if let Some(drawn) = Self::lock_available(db, candidates[index].item_id).await? {
+                break drawn;
+            }
+            tracing::info!("Item {} ran out under a concurrent draw", candidates[index].item_id);
+            candidates.remove(index);
+        };
+        let (total_rounds, roll) = match (drawn.rounds_dice(), drawn.rounds(), bag.rounds_dice()) {
+            (Some(dice), _, _) | (None, None, Some(dice)) => {
+                let roll = dice.roll(&mut rand::thread_rng());
+                (dice::rounds(&roll), Some(roll))
+            }
+            (None, Some(range), _) => (draw::roll_rounds(range, &mut rand::thread_rng()), None),
+            (None, None, None) => {
+                let range = rounds.for_size(&interface::ItemSize::from_repr(drawn.size).unwrap());
+                (draw::roll_rounds(range, &mut rand::thread_rng()), None)
+            }
+        };
+        let rounds_roll = roll
+            .map(|roll| serde_json::to_value(roll).map_err(|err| ModelError::Any(Box::new(err))))
+            .transpose()?;
+        let model = ActiveModel {
+            bag_id: ActiveValue::Set(bag.id),
+            item_id: ActiveValue::Set(drawn.id),
+            drawn_by: ActiveValue::Set(actor.user_id()),
+            rounds_left: ActiveValue::Set(total_rounds),
+            rounds_total: ActiveValue::Set(total_rounds),
+            rounds_roll: ActiveValue::Set(rounds_roll),
+            hand_slot: ActiveValue::Set(Some(slot)),
+            done: ActiveValue::Set(false),
+            paused: ActiveValue::Set(false),
+            rerolled: ActiveValue::Set(false),
+            ..Default::default()
+        }
+            .insert(db)
+            .await?;
+
+        // infinite items never run out, so their draws leave the stock alone
+        if !drawn.infinite {
+            inventory_entries::Model::record(db, &drawn, interface::InventoryKind::Draw, -1, None, Some(model.id)).await?;
+        }
+        audit_events::Model::record(db, actor, interface::AuditAction::Draw, None, Some(&model)).await?;
+        Ok(model)
+    }
+
+    /// Locks the item until the draw is saved, and returns it if it can still
+    /// be drawn. A concurrent draw into another slot of the hand may have
+    /// taken its last unit since the candidates were listed; it holds the
+    /// lock until it commits, so the stock is checked again once it has.
+    /// SQLite has no row locks, but lets only one transaction write at once.
+    async fn lock_available<C: ConnectionTrait>(db: &C, item_id: i32) -> Result<Option<items::Model>, DbErr> {
+        let Some(item) = items::Entity::find_by_id(item_id).lock_exclusive().one(db).await? else {
+            return Ok(None);
+        };
+        let available = items::Entity::find_by_id(item_id)
+            .filter(inventory_entries::Model::available_condition())
+            .count(db)
+            .await?;
+        Ok((available > 0).then_some(item))
+    }
+
+    /// Rejects the active or paused draw with the given id: its item goes
+    /// back into the bag without counting as drawn, and a new item is drawn
+    /// into its slot of the hand like [`Self::get_random`] does. The rejected
+    /// draw stays in the history as rerolled.
+    ///
+    /// With a `budget`, the bag allows that many rerolls per game, after
+    /// which the reroll fails with [`DrawError::RerollBudgetSpent`] until
+    /// [`game_rounds::Model::new_game`] starts the next one. The bag is
+    /// locked while the budget is checked, so concurrent rerolls never spend
+    /// more than it.
+    ///
+    /// # Errors
+    ///
+    /// When the draw is not in the hand of the bag, the budget is spent, the
+    /// bag has no available items or DB query error
+    #[allow(clippy::too_many_arguments)]
+    pub async fn reroll(db: &DatabaseConnection, bag: &bags::Model, actor: Actor<'_>, id: i32, strategy: &dyn DrawStrategy, rounds: &draw::RoundsBySize, budget: Option<i16>, tags: &interface::TagFilter) -> Result<interface::TakenItem, DrawError> {
+        let txn = db.begin().await?;
+        if budget.is_some() {
+            // concurrent rerolls of the bag wait for each other here, so they
+            // cannot both spend the last reroll of the game
+            bags::Entity::find_by_id(bag.id).lock_exclusive().one(&txn).await?;
+        }
+        let before = Self::find_in_hand(&txn, bag.id, id, None).await?;
+        let game = game_rounds::Model::current_game(&txn, bag.id).await?;
+        if let Some(budget) = budget {
+            let spent = taken_items::Entity::find()
+                .filter(taken_items::Column::BagId.eq(bag.id))
+                .filter(taken_items::Column::RerolledInGame.eq(game))
+                .count(&txn)
                 .await?;
+            if spent >= u64::try_from(budget).unwrap_or(0) {
+                return Err(DrawError::RerollBudgetSpent(budget));
+            }
+        }
 
-            txn.commit().await?;
-            Ok(model.into())
+        // only one of two concurrent rerolls of the draw gets to reject it
+        let rejected = taken_items::Entity::update_many()
+            .col_expr(taken_items::Column::Done, Expr::value(true))
+            .col_expr(taken_items::Column::Paused, Expr::value(false))
+            .col_expr(taken_items::Column::Rerolled, Expr::value(true))
+            .col_expr(taken_items::Column::RerolledInGame, Expr::value(game))
+            .col_expr(taken_items::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(taken_items::Column::Id.eq(before.id))
+            .filter(taken_items::Column::Done.eq(false))
+            .exec(&txn)
+            .await?;
+        if rejected.rows_affected == 0 {
+            return Err(ModelError::EntityNotFound.into());
+        }
+        let after = taken_items::Entity::find_by_id(before.id)
+            .one(&txn)
+            .await?
+            .ok_or(ModelError::EntityNotFound)?;
+        let item = items::Entity::find_by_id(after.item_id)
+            .one(&txn)
+            .await?
+            .ok_or(ModelError::EntityNotFound)?;
+        if !item.infinite {
+            inventory_entries::Model::record(&txn, &item, interface::InventoryKind::Reroll, 1, None, Some(after.id)).await?;
         }
+        audit_events::Model::record(&txn, actor, interface::AuditAction::Reroll, Some(&before), Some(&after)).await?;
+
+        let drawn = Self::draw_in_slot(&txn, bag, actor, strategy, rounds, before.hand_slot.unwrap_or(0), tags).await?;
+        txn.commit().await?;
+        Ok(drawn.into())
     }
 }
@@ -0,0 +1,85 @@
+use loco_rs::app::AppContext;
+use serde::Deserialize;
+
+use crate::draw::RoundsBySize;
+
+/// Application specific settings, read from the `settings` section of the
+/// environment config file.
+///
+/// ```yaml
+/// settings:
+///   draw_strategy: Weighted
+///   purge_archived_after_days: 30
+///   hand_size: 1
+///   reroll_budget: 3
+///   history_depth: 20
+///   rounds:
+///     small: { min: 1, max: 4 }
+///     medium: { min: 1, max: 6 }
+///     large: { min: 2, max: 8 }
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Settings {
+    /// Strategy used by bags that don't pick their own
+    #[serde(default)]
+    pub draw_strategy: interface::DrawStrategyKind,
+    /// How long archived items are kept before they can be purged
+    #[serde(default = "default_purge_archived_after_days")]
+    pub purge_archived_after_days: u32,
+    /// How many draws bags that don't pick their own hand size can hold at once
+    #[serde(default = "default_hand_size")]
+    pub hand_size: i16,
+    /// How many draws bags that don't set their own budget can reroll in a
+    /// game, unlimited when left out
+    #[serde(default)]
+    pub reroll_budget: Option<i16>,
+    /// How many changes to the draws of a bag can be undone, older ones are
+    /// forgotten
+    #[serde(default = "default_history_depth")]
+    pub history_depth: u64,
+    /// How many rounds drawn items last when they do not set their own
+    #[serde(default)]
+    pub rounds: RoundsBySize,
+}
+
+const fn default_purge_archived_after_days() -> u32 {
+    30
+}
+
+const fn default_hand_size() -> i16 {
+    1
+}
+
+const fn default_history_depth() -> u64 {
+    20
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            draw_strategy: interface::DrawStrategyKind::default(),
+            purge_archived_after_days: default_purge_archived_after_days(),
+            hand_size: default_hand_size(),
+            reroll_budget: None,
+            history_depth: default_history_depth(),
+            rounds: RoundsBySize::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings from the app context, falling back to the defaults
+    /// when the section is missing or cannot be parsed.
+    #[must_use]
+    pub fn from_context(ctx: &AppContext) -> Self {
+        ctx.config
+            .settings
+            .clone()
+            .and_then(|settings| {
+                serde_json::from_value(settings)
+                    .map_err(|err| tracing::warn!(%err, "invalid settings, using defaults"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+}
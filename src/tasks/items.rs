@@ -0,0 +1,133 @@
+//! Tasks for importing and exporting the items of a bag as CSV, JSON or YAML
+//! files, the same way the `items/import` and `items/export` endpoints do,
+//! and for purging archived items.
+//!
+//! # Example
+//!
+//! The format is taken from the file extension unless `format` is given:
+//! ```sh
+//! cargo run task import_items bag:1 file:items.csv
+//! cargo run task import_items bag:1 file:items.yaml dry_run:true upsert:true
+//! cargo run task export_items bag:1 file:items.json
+//! ```
+//!
+//! Exports without a `file` are printed.
+//!
+//! Items archived for longer than `purge_archived_after_days` from the
+//! settings, or `days` when given, are removed for good with:
+//! ```sh
+//! cargo run task purge_items days:7
+//! ```
+use std::{collections::BTreeMap, path::Path};
+
+use loco_rs::prelude::*;
+
+use crate::{item_files, models::{audit_events::Actor, bags, items}, settings::Settings};
+
+/// Reads the bag, file and format arguments shared by both tasks.
+fn arguments(vars: &BTreeMap<String, String>) -> Result<(i32, Option<&String>, interface::ItemFileFormat)> {
+    let bag_id = vars
+        .get("bag")
+        .and_then(|bag| bag.parse().ok())
+        .ok_or_else(|| Error::Message("a bag id is required, e.g. bag:1".to_string()))?;
+    let file = vars.get("file");
+
+    let format = match vars.get("format") {
+        Some(name) => Some(name.as_str()),
+        None => file.and_then(|file| Path::new(file).extension()).and_then(|ext| ext.to_str()),
+    };
+    let format = match format {
+        Some(name) => item_files::format_from_name(name)
+            .ok_or_else(|| Error::Message(format!("unknown format `{name}`, use csv, json or yaml")))?,
+        None => interface::ItemFileFormat::default(),
+    };
+    Ok((bag_id, file, format))
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct ImportItems;
+#[async_trait]
+impl Task for ImportItems {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "import_items".to_string(),
+            detail: "Import the items of a bag from a CSV, JSON or YAML file".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &BTreeMap<String, String>) -> Result<()> {
+        let (bag_id, file, format) = arguments(vars)?;
+        let file = file.ok_or_else(|| Error::Message("a file is required, e.g. file:items.csv".to_string()))?;
+        let bag = bags::Model::find_by_id(&app_context.db, bag_id).await?;
+
+        let content = std::fs::read_to_string(file)?;
+        let rows = item_files::parse(format, &content).map_err(|err| Error::Message(err.to_string()))?;
+        let options = interface::ItemImportOptions {
+            format,
+            dry_run: vars.get("dry_run").is_some_and(|dry_run| dry_run == "true"),
+            upsert: vars.get("upsert").is_some_and(|upsert| upsert == "true"),
+        };
+        let report = items::Model::import(&app_context.db, Actor::System, bag.id, rows, &options).await?;
+
+        for error in &report.errors {
+            println!("row {}: {}", error.row, error.errors.join("; "));
+        }
+        if !report.errors.is_empty() {
+            return Err(Error::Message(format!("{} invalid rows, nothing was imported", report.errors.len())));
+        }
+        let verb = if report.dry_run { "would be" } else { "were" };
+        println!("{} items {verb} created and {} {verb} updated", report.created, report.updated);
+        Ok(())
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct ExportItems;
+#[async_trait]
+impl Task for ExportItems {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "export_items".to_string(),
+            detail: "Export the items of a bag to a CSV, JSON or YAML file".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &BTreeMap<String, String>) -> Result<()> {
+        let (bag_id, file, format) = arguments(vars)?;
+        let bag = bags::Model::find_by_id(&app_context.db, bag_id).await?;
+
+        let items = items::Model::export(&app_context.db, bag.id).await?;
+        let content = item_files::write(format, items).map_err(|err| Error::Message(err.to_string()))?;
+        match file {
+            Some(file) => std::fs::write(file, content)?,
+            None => print!("{content}"),
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct PurgeItems;
+#[async_trait]
+impl Task for PurgeItems {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "purge_items".to_string(),
+            detail: "Permanently remove items archived longer than the configured age".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &BTreeMap<String, String>) -> Result<()> {
+        let days = match vars.get("days") {
+            Some(days) => days
+                .parse()
+                .map_err(|_| Error::Message(format!("`{days}` is not a number of days")))?,
+            None => Settings::from_context(app_context).purge_archived_after_days,
+        };
+
+        let archived_before = chrono::Utc::now().naive_utc() - chrono::Duration::days(i64::from(days));
+        let purged = items::Model::purge(&app_context.db, Actor::System, archived_before).await?;
+        println!("{purged} items archived more than {days} days ago were purged");
+        Ok(())
+    }
+}
@@ -13,12 +13,104 @@
 //! ```sh
 //! cargo run task seed_data refresh:true
 //! ```
+//!
+//! To also get a bag filled with random items and a few past draws, add
+//! `demo:true`. The bag belongs to the first fixture user unless `owner` names
+//! another one by email:
+//! ```sh
+//! cargo run task seed_data refresh:true demo:true owner:user2@example.com
+//! ```
 use std::collections::BTreeMap;
 
 use loco_rs::{db, prelude::*};
 use migration::Migrator;
+use rand::{seq::SliceRandom, Rng};
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    app::App,
+    draw,
+    models::{audit_events::Actor, bags, items, taken_items, users},
+    settings::Settings,
+};
 
-use crate::app::App;
+/// Items a demo bag is filled from: name, description, size and tags.
+const DEMO_ITEMS: &[(&str, Option<&str>, interface::ItemSize, &[&str])] = &[
+    ("Torch", Some("Burns for an hour"), interface::ItemSize::Small, &["light"]),
+    ("Lantern", Some("Needs oil to keep burning"), interface::ItemSize::Medium, &["light"]),
+    ("Rope", Some("Fifty feet of hempen rope"), interface::ItemSize::Medium, &["tool"]),
+    ("Grappling hook", None, interface::ItemSize::Small, &["tool"]),
+    ("Crowbar", None, interface::ItemSize::Medium, &["tool", "metal"]),
+    ("Healing potion", Some("Restores a handful of hit points"), interface::ItemSize::Small, &["consumable", "magic"]),
+    ("Antidote", None, interface::ItemSize::Small, &["consumable"]),
+    ("Rations", Some("Dried meat and hard bread for a day"), interface::ItemSize::Small, &["consumable"]),
+    ("Dagger", None, interface::ItemSize::Small, &["weapon", "metal"]),
+    ("Longsword", None, interface::ItemSize::Medium, &["weapon", "metal"]),
+    ("Shortbow", Some("Comes with a quiver of arrows"), interface::ItemSize::Medium, &["weapon"]),
+    ("Tower shield", Some("Heavy, but nothing gets through"), interface::ItemSize::Large, &["armor", "metal"]),
+    ("Chain mail", None, interface::ItemSize::Large, &["armor", "metal"]),
+    ("Scroll of fireball", None, interface::ItemSize::Small, &["consumable", "magic"]),
+    ("Wand of light", None, interface::ItemSize::Small, &["light", "magic"]),
+    ("Bedroll", None, interface::ItemSize::Large, &["camping"]),
+    ("Tinderbox", None, interface::ItemSize::Small, &["camping", "tool"]),
+    ("Gold coins", Some("A pouch of shiny coins"), interface::ItemSize::Small, &["treasure"]),
+];
+
+/// Picks the items of a demo bag with random quantities and weights.
+fn demo_items() -> Vec<interface::CreateUpdateItem> {
+    let mut rng = rand::thread_rng();
+    let count = rng.gen_range(8..=12);
+    DEMO_ITEMS
+        .choose_multiple(&mut rng, count)
+        .map(|(name, description, size, tags)| interface::CreateUpdateItem {
+            name: (*name).to_string(),
+            description: description.map(ToString::to_string),
+            quantity: rng.gen_range(1..=4),
+            size: size.clone(),
+            infinite: rng.gen_ratio(1, 10),
+            weight: rng.gen_range(1..=5),
+            tags: tags.iter().map(ToString::to_string).collect(),
+            rounds: None,
+            rounds_dice: None,
+        })
+        .collect()
+}
+
+/// Creates a bag of random items for `owner`, with a few finished draws and
+/// one active draw.
+async fn demo_bag(db: &DatabaseConnection, owner: &users::Model, settings: &Settings) -> Result<bags::Model> {
+    let bag = bags::Model::create(
+        db,
+        owner.id,
+        interface::CreateUpdateBag {
+            name: "Demo bag".to_string(),
+            draw_strategy: None,
+            auto_restock: true,
+            rounds_dice: None,
+            hand_size: None,
+            reroll_budget: None,
+        },
+    )
+    .await?;
+    for item in demo_items() {
+        items::Model::create(db, Actor::User(owner), bag.id, item).await?;
+    }
+
+    let draws = rand::thread_rng().gen_range(3..=8);
+    let strategy = draw::strategy_for(interface::DrawStrategyKind::default());
+    for draw in 0..draws {
+        match taken_items::Model::get_random(db, &bag, Actor::User(owner), strategy.as_ref(), &settings.rounds, settings.hand_size, &interface::TagFilter::default()).await {
+            Ok(_) => {}
+            Err(taken_items::DrawError::BagExhausted(_)) => break,
+            Err(taken_items::DrawError::Model(err)) => return Err(err.into()),
+            Err(err @ (taken_items::DrawError::RerollBudgetSpent(_) | taken_items::DrawError::Concurrent(_))) => return Err(Error::Message(err.to_string())),
+        }
+        if draw + 1 < draws {
+            taken_items::Model::mark_done(db, Actor::User(owner), bag.id, settings.history_depth).await?;
+        }
+    }
+    Ok(bag)
+}
 
 #[allow(clippy::module_name_repetitions)]
 pub struct SeedData;
@@ -33,12 +125,20 @@ impl Task for SeedData {
 
     async fn run(&self, app_context: &AppContext, vars: &BTreeMap<String, String>) -> Result<()> {
         let refresh = vars.get("refresh").is_some_and(|refresh| refresh == "true");
+        let demo = vars.get("demo").is_some_and(|demo| demo == "true");
 
         if refresh {
             db::reset::<Migrator>(&app_context.db).await?;
         }
         let path = std::path::Path::new("src/fixtures");
         db::run_app_seed::<App>(&app_context.db, path).await?;
+
+        if demo {
+            let email = vars.get("owner").map_or("user1@example.com", String::as_str);
+            let owner = users::Model::find_by_email(&app_context.db, email).await?;
+            let bag = demo_bag(&app_context.db, &owner, &Settings::from_context(app_context)).await?;
+            tracing::info!("Created demo bag {} for {}", bag.id, owner.email);
+        }
         Ok(())
     }
 }
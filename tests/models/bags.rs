@@ -0,0 +1,65 @@
+use insta::assert_debug_snapshot;
+use loco_rs::{model::ModelError, testing};
+use roadiebag2::app::App;
+use roadiebag2::models::{audit_events::Actor, bags, items};
+use serial_test::serial;
+use super::prepare_data;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("bags");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn can_list_for_user() {
+    configure_insta!();
+
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let owner_bags = bags::Model::list_for_user(&boot.app_context.db, bag.user_id).await.unwrap();
+    let other_bags = bags::Model::list_for_user(&boot.app_context.db, 2).await.unwrap();
+
+    insta::with_settings!({
+        filters => testing::CLEANUP_DATE.to_vec()
+    }, {
+        assert_debug_snapshot!((owner_bags, other_bags))
+    });
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_find_other_users_bag() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    assert!(bags::Model::find_for_user(&boot.app_context.db, bag.id, bag.user_id).await.is_ok());
+    assert!(matches!(
+        bags::Model::find_for_user(&boot.app_context.db, bag.id, 2).await,
+        Err(ModelError::EntityNotFound)
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn can_delete_with_items() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let create = interface::CreateUpdateItem {
+        name: "Test item".to_string(),
+        quantity: 2,
+        ..Default::default()
+    };
+    let item = items::Model::create(&boot.app_context.db, Actor::System, bag.id, create).await.unwrap();
+
+    bags::Model::delete(&boot.app_context.db, bag.id).await.unwrap();
+
+    assert!(bags::Model::find_for_user(&boot.app_context.db, bag.id, bag.user_id).await.is_err());
+    assert!(items::Model::find_by_id(&boot.app_context.db, bag.id, item.id).await.is_err());
+}
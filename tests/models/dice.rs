@@ -0,0 +1,73 @@
+use rand::{rngs::StdRng, SeedableRng};
+use roadiebag2::dice::{self, DiceError, DiceExpression};
+
+fn parse(expression: &str) -> Result<String, DiceError> {
+    expression.parse::<DiceExpression>().map(|dice| dice.to_string())
+}
+
+#[test]
+fn parses_dice_notation() {
+    assert_eq!(parse("1d4+1"), Ok("1d4+1".to_string()));
+    assert_eq!(parse("d8 + D4"), Ok("1d8+1d4".to_string()));
+    assert_eq!(parse("2d6 keep highest"), Ok("2d6kh1".to_string()));
+    assert_eq!(parse("4d6 keep lowest 2 - 1"), Ok("4d6kl2-1".to_string()));
+    assert_eq!(parse("4d6k3"), Ok("4d6kh3".to_string()));
+    assert_eq!(parse("-1+2d4"), Ok("-1+2d4".to_string()));
+    assert_eq!(parse(" 3 "), Ok("3".to_string()));
+}
+
+#[test]
+fn rejects_invalid_notation() {
+    assert_eq!(parse(""), Err(DiceError::Empty));
+    assert_eq!(parse("2d"), Err(DiceError::MissingSides));
+    assert_eq!(parse("1d4+"), Err(DiceError::Empty));
+    assert_eq!(parse("1d4 x"), Err(DiceError::Unexpected(4)));
+    assert_eq!(parse("2d6 keep most"), Err(DiceError::Unexpected(9)));
+    assert_eq!(parse("0d6"), Err(DiceError::TooManyDice(0)));
+    assert_eq!(parse("1d0"), Err(DiceError::TooManySides(0)));
+    assert_eq!(parse("2d6kh3"), Err(DiceError::InvalidKeep { keep: 3, count: 2 }));
+    assert_eq!(parse("5000"), Err(DiceError::ConstantTooLarge(5000)));
+    assert_eq!(parse(&["1"; 21].join("+")), Err(DiceError::TooManyTerms));
+}
+
+#[test]
+fn rolls_keep_the_right_dice() {
+    let mut rng = StdRng::seed_from_u64(31);
+    let highest: DiceExpression = "3d20 keep highest 2 + 1".parse().unwrap();
+    let lowest: DiceExpression = "3d20kl1".parse().unwrap();
+    for _ in 0..100 {
+        let roll = highest.roll(&mut rng);
+        assert_eq!(roll.expression, "3d20kh2+1");
+        let rolls = &roll.dice[0];
+        assert_eq!(rolls.dice, "3d20kh2");
+        assert_eq!(rolls.rolls.len(), 3);
+        let mut sorted = rolls.rolls.clone();
+        sorted.sort_unstable();
+        assert_eq!(rolls.kept.iter().sum::<u32>(), sorted[1] + sorted[2]);
+        assert_eq!(roll.total, i32::try_from(sorted[1] + sorted[2]).unwrap() + 1);
+
+        let roll = lowest.roll(&mut rng);
+        assert_eq!(roll.dice[0].kept, [*roll.dice[0].rolls.iter().min().unwrap()]);
+    }
+}
+
+#[test]
+fn rolls_stay_within_their_bounds() {
+    let mut rng = StdRng::seed_from_u64(37);
+    let dice: DiceExpression = "1d4+1".parse().unwrap();
+    let mut seen = [false; 4];
+    for _ in 0..1000 {
+        let roll = dice.roll(&mut rng);
+        assert!((2..=5).contains(&roll.total), "rolled {}", roll.total);
+        seen[usize::try_from(roll.total - 2).unwrap()] = true;
+    }
+    assert!(seen.iter().all(|&seen| seen));
+}
+
+#[test]
+fn draws_last_at_least_a_round() {
+    let mut rng = StdRng::seed_from_u64(41);
+    let roll = "1d4-10".parse::<DiceExpression>().unwrap().roll(&mut rng);
+    assert!(roll.total < 0);
+    assert_eq!(dice::rounds(&roll), 1);
+}
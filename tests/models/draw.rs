@@ -0,0 +1,137 @@
+use rand::{rngs::StdRng, SeedableRng};
+use roadiebag2::draw::{self, Candidate, DrawStrategy};
+
+const DRAWS: usize = 60_000;
+
+fn candidates(weights: &[i32]) -> Vec<Candidate> {
+    weights
+        .iter()
+        .enumerate()
+        .map(|(i, &weight)| Candidate {
+            item_id: i32::try_from(i).unwrap() + 1,
+            weight,
+            times_drawn: 0,
+        })
+        .collect()
+}
+
+/// Pearson's chi-square statistic of the observed counts against the
+/// expected proportions.
+fn chi_square(observed: &[usize], expected: &[f64]) -> f64 {
+    let total: usize = observed.iter().sum();
+    let sum: f64 = expected.iter().sum();
+    observed
+        .iter()
+        .zip(expected)
+        .map(|(&o, &e)| {
+            let e = e / sum * total as f64;
+            (o as f64 - e).powi(2) / e
+        })
+        .sum()
+}
+
+fn histogram(strategy: &dyn DrawStrategy, candidates: &[Candidate], rng: &mut StdRng) -> Vec<usize> {
+    let mut counts = vec![0; candidates.len()];
+    for _ in 0..DRAWS {
+        counts[strategy.choose(candidates, rng).unwrap()] += 1;
+    }
+    counts
+}
+
+// critical values of the chi-square distribution at p = 0.001
+const CHI2_3_DOF: f64 = 16.27;
+const CHI2_5_DOF: f64 = 20.52;
+
+#[test]
+fn uniform_ignores_weights() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let counts = histogram(&draw::Uniform, &candidates(&[1, 2, 3, 10]), &mut rng);
+
+    let chi2 = chi_square(&counts, &[1.0; 4]);
+    assert!(chi2 < CHI2_3_DOF, "chi2 = {chi2}, counts = {counts:?}");
+}
+
+#[test]
+fn weighted_follows_weights() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let counts = histogram(&draw::Weighted, &candidates(&[1, 2, 3, 10]), &mut rng);
+
+    let chi2 = chi_square(&counts, &[1.0, 2.0, 3.0, 10.0]);
+    assert!(chi2 < CHI2_3_DOF, "chi2 = {chi2}, counts = {counts:?}");
+}
+
+#[test]
+fn first_and_last_items_are_not_favoured() {
+    // the old clamp based selection picked the last item almost every time
+    let mut rng = StdRng::seed_from_u64(13);
+    let items = candidates(&[1; 4]);
+    for kind in [
+        interface::DrawStrategyKind::Uniform,
+        interface::DrawStrategyKind::Weighted,
+        interface::DrawStrategyKind::ShuffleBag,
+    ] {
+        let strategy = draw::strategy_for(kind);
+        let counts = histogram(strategy.as_ref(), &items, &mut rng);
+        let chi2 = chi_square(&counts, &[1.0; 4]);
+        assert!(chi2 < CHI2_3_DOF, "chi2 = {chi2}, counts = {counts:?}");
+    }
+}
+
+#[test]
+fn shuffle_bag_empties_a_cycle_before_refilling() {
+    let mut rng = StdRng::seed_from_u64(17);
+    let mut items = candidates(&[1, 2, 3]);
+
+    for cycle in 1..=50 {
+        for _ in 0..6 {
+            let index = draw::ShuffleBag.choose(&items, &mut rng).unwrap();
+            items[index].times_drawn += 1;
+        }
+        for item in &items {
+            assert_eq!(item.times_drawn, i64::from(item.weight) * cycle, "{items:?}");
+        }
+    }
+}
+
+#[test]
+fn shuffle_bag_counts_tokens_left_in_cycle() {
+    let mut items = candidates(&[1, 2, 3]);
+    items[1].times_drawn = 2;
+    items[2].times_drawn = 1;
+
+    assert_eq!(draw::ShuffleBag::remaining_tokens(&items), vec![1, 0, 2]);
+}
+
+#[test]
+fn nothing_to_choose_from() {
+    let mut rng = StdRng::seed_from_u64(19);
+    for kind in [
+        interface::DrawStrategyKind::Uniform,
+        interface::DrawStrategyKind::Weighted,
+        interface::DrawStrategyKind::ShuffleBag,
+    ] {
+        assert_eq!(draw::strategy_for(kind).choose(&[], &mut rng), None);
+    }
+}
+
+#[test]
+fn rounds_are_uniform_within_the_range() {
+    let mut rng = StdRng::seed_from_u64(23);
+    let range = interface::RoundRange { min: 3, max: 8 };
+    let mut counts = vec![0; 6];
+    for _ in 0..DRAWS {
+        let rounds = draw::roll_rounds(range, &mut rng);
+        assert!((3..=8).contains(&rounds), "rolled {rounds}");
+        counts[usize::try_from(rounds - 3).unwrap()] += 1;
+    }
+
+    let chi2 = chi_square(&counts, &[1.0; 6]);
+    assert!(chi2 < CHI2_5_DOF, "chi2 = {chi2}, counts = {counts:?}");
+}
+
+#[test]
+fn fixed_rounds_always_roll_the_same() {
+    let mut rng = StdRng::seed_from_u64(29);
+    let range = interface::RoundRange { min: 4, max: 4 };
+    assert!((0..100).all(|_| draw::roll_rounds(range, &mut rng) == 4));
+}
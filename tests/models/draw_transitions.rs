@@ -0,0 +1,101 @@
+use roadiebag2::app::App;
+use loco_rs::testing;
+use serial_test::serial;
+use roadiebag2::draw;
+use roadiebag2::models::{audit_events::Actor, bags, draw_transitions::{self, HistoryError}, game_rounds, items, taken_items};
+use super::prepare_data::{self, HISTORY_DEPTH};
+
+async fn add_item(db: &sea_orm::DatabaseConnection, bag: &bags::Model, name: &str, rounds: i16) {
+    items::Model::create(db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: name.to_string(),
+        rounds: Some(interface::RoundRange { min: rounds, max: rounds }),
+        ..Default::default()
+    }).await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn undo_and_redo_draw_changes() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let bag = prepare_data::init_bag(db).await;
+    for name in ["Torch", "Rope", "Rations", "Lantern"] {
+        add_item(db, &bag, name, 2).await;
+    }
+    let (rounds, tags) = (draw::RoundsBySize::default(), interface::TagFilter::default());
+    let draw = || taken_items::Model::get_random(db, &bag, Actor::System, &draw::Weighted, &rounds, 2, &tags);
+    let first = draw().await.unwrap();
+    let second = draw().await.unwrap();
+
+    assert!(matches!(draw_transitions::Model::undo(db, Actor::System, bag.id).await, Err(HistoryError::Empty(_))));
+
+    taken_items::Model::decrement_draw(db, Actor::System, bag.id, first.id, HISTORY_DEPTH).await.unwrap();
+    let undone = draw_transitions::Model::undo(db, Actor::System, bag.id).await.unwrap();
+    assert_eq!(undone.action, interface::AuditAction::Decrement);
+    assert!(undone.undone);
+    assert_eq!((undone.taken[0].id, undone.taken[0].rounds_left), (first.id, 2));
+    let redone = draw_transitions::Model::redo(db, Actor::System, bag.id).await.unwrap();
+    assert_eq!((redone.id, redone.undone), (undone.id, false));
+    assert_eq!(redone.taken[0].rounds_left, 1);
+    assert!(matches!(draw_transitions::Model::redo(db, Actor::System, bag.id).await, Err(HistoryError::Empty(_))));
+
+    // advancing the round is final, and so is everything before it
+    let summary = game_rounds::Model::advance(db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    assert_eq!((summary.expired[0].taken.id, summary.active[0].id), (first.id, second.id));
+    assert!(matches!(draw_transitions::Model::undo(db, Actor::System, bag.id).await, Err(HistoryError::RoundAdvanced(_))));
+    assert_eq!(game_rounds::Model::current(db, bag.id).await.unwrap().round, 1);
+
+    // a new change forgets what was undone
+    taken_items::Model::mark_draw_done(db, Actor::System, bag.id, second.id, HISTORY_DEPTH).await.unwrap();
+    let undone = draw_transitions::Model::undo(db, Actor::System, bag.id).await.unwrap();
+    assert_eq!((undone.action, undone.taken[0].status), (interface::AuditAction::Done, interface::TakenStatus::Active));
+    taken_items::Model::decrement_draw(db, Actor::System, bag.id, second.id, HISTORY_DEPTH).await.unwrap();
+    assert!(matches!(draw_transitions::Model::redo(db, Actor::System, bag.id).await, Err(HistoryError::Empty(_))));
+
+    // the finished draw cannot come back once another draw took its slot
+    let (third, fourth) = (draw().await.unwrap(), draw().await.unwrap());
+    assert!(matches!(draw_transitions::Model::undo(db, Actor::System, bag.id).await, Err(HistoryError::Conflict(_))));
+    let hand: Vec<_> = taken_items::Model::get_hand(db, bag.id).await.unwrap().into_iter().map(|taken| taken.id).collect();
+    assert_eq!(hand, [third.id, fourth.id]);
+}
+
+#[tokio::test]
+#[serial]
+async fn advancing_a_paused_hand_is_final() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let bag = prepare_data::init_bag(db).await;
+    add_item(db, &bag, "Torch", 3).await;
+    let drawn = taken_items::Model::get_random(db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+    taken_items::Model::decrement_draw(db, Actor::System, bag.id, drawn.id, HISTORY_DEPTH).await.unwrap();
+    taken_items::Model::pause(db, Actor::System, bag.id, drawn.id, HISTORY_DEPTH).await.unwrap();
+
+    // no draw changes, but the round still cannot be undone
+    let summary = game_rounds::Model::advance(db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    assert!(summary.expired.is_empty());
+    assert!(matches!(draw_transitions::Model::undo(db, Actor::System, bag.id).await, Err(HistoryError::RoundAdvanced(_))));
+    let hand = taken_items::Model::get_hand(db, bag.id).await.unwrap();
+    assert_eq!((hand[0].rounds_left, hand[0].status), (2, interface::TakenStatus::Paused));
+}
+
+#[tokio::test]
+#[serial]
+async fn history_depth_is_bounded() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let bag = prepare_data::init_bag(db).await;
+    add_item(db, &bag, "Torch", 100).await;
+    let drawn = taken_items::Model::get_random(db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+
+    // the depth comes from the settings, and can be smaller than the default
+    let depth = 3;
+    for _ in 0..depth + 5 {
+        taken_items::Model::decrement_draw(db, Actor::System, bag.id, drawn.id, depth).await.unwrap();
+    }
+    let mut last = None;
+    for _ in 0..depth {
+        last = Some(draw_transitions::Model::undo(db, Actor::System, bag.id).await.unwrap());
+    }
+    assert_eq!(last.unwrap().taken[0].rounds_left, 100 - 5);
+    assert!(matches!(draw_transitions::Model::undo(db, Actor::System, bag.id).await, Err(HistoryError::Empty(_))));
+}
@@ -0,0 +1,67 @@
+use roadiebag2::app::App;
+use loco_rs::testing;
+use serial_test::serial;
+use roadiebag2::draw;
+use roadiebag2::models::{audit_events::Actor, game_rounds, items, taken_items};
+use super::prepare_data::{self, HISTORY_DEPTH};
+
+#[tokio::test]
+#[serial]
+async fn advancing_a_round_expires_draws() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let round = game_rounds::Model::current(&boot.app_context.db, bag.id).await.unwrap();
+    assert_eq!((round.game, round.round, round.started_at), (1, 0, None));
+
+    for (name, rounds) in [("Torch", 1), ("Lantern", 2)] {
+        items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+            name: name.to_string(),
+            tags: vec!["light".to_string()],
+            rounds: Some(interface::RoundRange { min: rounds, max: rounds }),
+            ..Default::default()
+        }).await.unwrap();
+    }
+    for _ in 0..2 {
+        taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 2, &interface::TagFilter::default()).await.unwrap();
+    }
+
+    let first = game_rounds::Model::advance(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    assert_eq!(first.round.round, 1);
+    assert_eq!(first.active.len(), 1);
+    assert_eq!(first.active[0].rounds_left, 1);
+    assert_eq!(first.expired.len(), 1);
+    assert_eq!(first.expired[0].item.name, "Torch");
+    assert_eq!(first.expired[0].item.tags, ["light"]);
+    assert_eq!(first.expired[0].taken.status, interface::TakenStatus::Done);
+
+    let second = game_rounds::Model::advance(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    assert_eq!(second.round.round, 2);
+    assert!(second.active.is_empty());
+    assert_eq!(second.expired[0].item.name, "Lantern");
+
+    let third = game_rounds::Model::advance(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    assert_eq!(third.round.round, 3);
+    assert!(third.active.is_empty() && third.expired.is_empty());
+    assert!(taken_items::Model::get_hand(&boot.app_context.db, bag.id).await.unwrap().is_empty());
+    assert_eq!(game_rounds::Model::current(&boot.app_context.db, bag.id).await.unwrap(), third.round);
+}
+
+#[tokio::test]
+#[serial]
+async fn new_games_start_at_round_zero() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let bag = prepare_data::init_bag(db).await;
+
+    game_rounds::Model::advance(db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    game_rounds::Model::advance(db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    let started = game_rounds::Model::new_game(db, Actor::System, bag.id).await.unwrap();
+    assert_eq!((started.game, started.round), (2, 0));
+    assert!(started.started_at.is_some());
+    assert_eq!(game_rounds::Model::current(db, bag.id).await.unwrap(), started);
+
+    let advanced = game_rounds::Model::advance(db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    assert_eq!((advanced.round.game, advanced.round.round), (2, 1));
+    assert_eq!(game_rounds::Model::current_game(db, bag.id).await.unwrap(), 2);
+}
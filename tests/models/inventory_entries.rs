@@ -0,0 +1,106 @@
+use roadiebag2::app::App;
+use loco_rs::testing;
+use serial_test::serial;
+use roadiebag2::draw;
+use roadiebag2::models::{audit_events::Actor, bags, inventory_entries::{self, StockError}, items, taken_items};
+use super::prepare_data::{self, HISTORY_DEPTH};
+
+#[tokio::test]
+#[serial]
+async fn draws_follow_the_ledger() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let item = items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Rope".to_string(),
+        quantity: 2,
+        size: interface::ItemSize::Medium,
+        ..Default::default()
+    }).await.unwrap();
+    for _ in 0..2 {
+        taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+        taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    }
+    assert_eq!(inventory_entries::Model::balance(&boot.app_context.db, item.id).await.unwrap(), 0);
+    let drawn = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await;
+    assert!(matches!(drawn, Err(taken_items::DrawError::BagExhausted(_))));
+
+    // the stock cannot go below zero
+    let removed = inventory_entries::Model::adjust(&boot.app_context.db, Actor::System, bag.id, item.id, interface::AdjustStock {
+        delta: -1,
+        reason: "lost in the river".to_string(),
+    }).await;
+    assert!(matches!(removed, Err(StockError::Insufficient(0))));
+    let restocked = inventory_entries::Model::restock(&boot.app_context.db, Actor::System, bag.id, item.id, interface::Restock {
+        amount: Some(0),
+        reason: None,
+    }).await;
+    assert!(matches!(restocked, Err(StockError::InvalidAmount(0))));
+
+    // a restock refills the item up to its quantity, so it can be drawn again
+    inventory_entries::Model::restock(&boot.app_context.db, Actor::System, bag.id, item.id, interface::Restock::default()).await.unwrap();
+    assert_eq!(inventory_entries::Model::balance(&boot.app_context.db, item.id).await.unwrap(), 2);
+    inventory_entries::Model::adjust(&boot.app_context.db, Actor::System, bag.id, item.id, interface::AdjustStock {
+        delta: -1,
+        reason: "lost in the river".to_string(),
+    }).await.unwrap();
+    taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+    taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    let drawn = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await;
+    assert!(matches!(drawn, Err(taken_items::DrawError::BagExhausted(_))));
+
+    // raising the quantity puts the new items in stock
+    items::Model::update(&boot.app_context.db, Actor::System, bag.id, item.id, None, interface::CreateUpdateItem {
+        name: "Rope".to_string(),
+        quantity: 3,
+        size: interface::ItemSize::Medium,
+        ..Default::default()
+    }).await.unwrap();
+    let item = items::Model::find_by_id(&boot.app_context.db, bag.id, item.id).await.unwrap()
+        .into_item(&boot.app_context.db).await.unwrap();
+    assert_eq!(item.remaining, Some(1));
+
+    let ledger = inventory_entries::Model::list(&boot.app_context.db, bag.id, item.id).await.unwrap();
+    let entries: Vec<_> = ledger.iter().rev().map(|entry| (entry.kind, entry.delta, entry.reason.as_deref())).collect();
+    assert_eq!(entries, [
+        (interface::InventoryKind::Add, 2, None),
+        (interface::InventoryKind::Draw, -1, None),
+        (interface::InventoryKind::Draw, -1, None),
+        (interface::InventoryKind::Restock, 2, None),
+        (interface::InventoryKind::Remove, -1, Some("lost in the river")),
+        (interface::InventoryKind::Draw, -1, None),
+        (interface::InventoryKind::Add, 1, Some("quantity changed")),
+    ]);
+    assert!(ledger.iter().filter(|entry| entry.kind == interface::InventoryKind::Draw).all(|entry| entry.taken_item_id.is_some()));
+}
+
+#[tokio::test]
+#[serial]
+async fn auto_restock_refills_the_ledger() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+    let bag = bags::Model::update(&boot.app_context.db, bag.id, interface::CreateUpdateBag {
+        name: bag.name,
+        draw_strategy: None,
+        auto_restock: true,
+        rounds_dice: None,
+        hand_size: None,
+        reroll_budget: None,
+    }).await.unwrap();
+
+    let item = items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Arrow".to_string(),
+        ..Default::default()
+    }).await.unwrap();
+    for _ in 0..3 {
+        let drawn = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+        assert_eq!(drawn.item_id, item.id);
+        taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    }
+    assert_eq!(inventory_entries::Model::balance(&boot.app_context.db, item.id).await.unwrap(), 0);
+    let restocks = inventory_entries::Model::list(&boot.app_context.db, bag.id, item.id).await.unwrap()
+        .into_iter()
+        .filter(|entry| entry.kind == interface::InventoryKind::Restock)
+        .count();
+    assert_eq!(restocks, 2);
+}
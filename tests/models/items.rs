@@ -1,8 +1,10 @@
 use insta::assert_debug_snapshot;
 use roadiebag2::app::App;
-use loco_rs::testing;
+use loco_rs::{model::ModelError, testing};
 use serial_test::serial;
-use roadiebag2::models::items;
+use roadiebag2::draw;
+use roadiebag2::models::{audit_events::Actor, items, taken_items};
+use super::prepare_data::{self, HISTORY_DEPTH};
 
 macro_rules! configure_insta {
     ($($expr:expr),*) => {
@@ -18,16 +20,15 @@ async fn test_create() {
     configure_insta!();
 
     let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
 
     let create = interface::CreateUpdateItem {
         name: "Test item".to_string(),
-        description: None,
         quantity: 2,
-        size: interface::ItemSize::Small,
-        infinite: false
+        ..Default::default()
     };
 
-    let model = items::Model::create(&boot.app_context.db, create).await;
+    let model = items::Model::create(&boot.app_context.db, Actor::System, bag.id, create).await;
     insta::with_settings!({
         filters => testing::CLEANUP_DATE.to_vec()
     }, {
@@ -41,17 +42,16 @@ async fn test_update() {
     configure_insta!();
 
     let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
 
     let create = interface::CreateUpdateItem {
         name: "Test item".to_string(),
-        description: None,
         quantity: 2,
-        size: interface::ItemSize::Small,
-        infinite: false
+        ..Default::default()
     };
 
-    let model = items::Model::create(&boot.app_context.db, create.clone()).await;
-    assert_eq!(model.is_ok(), true);
+    let model = items::Model::create(&boot.app_context.db, Actor::System, bag.id, create.clone()).await;
+    assert!(model.is_ok());
     let model = model.unwrap();
 
     let update = interface::CreateUpdateItem {
@@ -59,8 +59,8 @@ async fn test_update() {
         ..create
     };
 
-    let model2 = items::Model::update(&boot.app_context.db, model.id, update).await;
-    assert_eq!(model2.is_ok(), true);
+    let model2 = items::Model::update(&boot.app_context.db, Actor::System, bag.id, model.id, None, update).await;
+    assert!(model2.is_ok());
 
     insta::with_settings!({
         filters => testing::CLEANUP_DATE.to_vec()
@@ -75,22 +75,21 @@ async fn can_delete() {
     configure_insta!();
 
     let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
 
-    let starting_list = items::Model::list(&boot.app_context.db, None).await.unwrap();
+    let starting_list = items::Model::list(&boot.app_context.db, bag.id, None).await.unwrap();
 
     let create = interface::CreateUpdateItem {
         name: "Test item".to_string(),
-        description: None,
         quantity: 2,
-        size: interface::ItemSize::Small,
-        infinite: false
+        ..Default::default()
     };
 
-    let item = items::Model::create(&boot.app_context.db, create).await.unwrap();
-    let after_create_list = items::Model::list(&boot.app_context.db, None).await.unwrap();
+    let item = items::Model::create(&boot.app_context.db, Actor::System, bag.id, create).await.unwrap();
+    let after_create_list = items::Model::list(&boot.app_context.db, bag.id, None).await.unwrap();
 
-    items::Model::delete(&boot.app_context.db, item.id).await.unwrap();
-    let after_delete_list = items::Model::list(&boot.app_context.db, None).await.unwrap();
+    items::Model::delete(&boot.app_context.db, Actor::System, bag.id, item.id).await.unwrap();
+    let after_delete_list = items::Model::list(&boot.app_context.db, bag.id, None).await.unwrap();
 
     insta::with_settings!({
         filters => testing::CLEANUP_DATE.to_vec()
@@ -109,38 +108,36 @@ async fn can_filter() {
     configure_insta!();
 
     let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
 
-    let starting_list = items::Model::list(&boot.app_context.db, None).await.unwrap();
+    let starting_list = items::Model::list(&boot.app_context.db, bag.id, None).await.unwrap();
 
     let create = interface::CreateUpdateItem {
         name: "Test item".to_string(),
-        description: None,
         quantity: 2,
-        size: interface::ItemSize::Small,
-        infinite: false
+        ..Default::default()
     };
     let create2 = interface::CreateUpdateItem {
         name: "Test item2".to_string(),
-        description: None,
-        quantity: 1,
         size: interface::ItemSize::Medium,
-        infinite: true
+        infinite: true,
+        ..Default::default()
     };
-    let item1 = items::Model::create(&boot.app_context.db, create).await.unwrap();
-    let item2 = items::Model::create(&boot.app_context.db, create2).await.unwrap();
-    let all_items = items::Model::list(&boot.app_context.db, None).await.unwrap();
+    items::Model::create(&boot.app_context.db, Actor::System, bag.id, create).await.unwrap();
+    items::Model::create(&boot.app_context.db, Actor::System, bag.id, create2).await.unwrap();
+    let all_items = items::Model::list(&boot.app_context.db, bag.id, None).await.unwrap();
 
     let small_filter = interface::ItemFilter {
         size: Some(interface::ItemSize::Small),
         ..Default::default()
     };
-    let small_items = items::Model::list(&boot.app_context.db, Some(small_filter)).await.unwrap();
+    let small_items = items::Model::list(&boot.app_context.db, bag.id, Some(small_filter)).await.unwrap();
 
     let inf_filter = interface::ItemFilter {
         infinite: Some(true),
         ..Default::default()
     };
-    let inf_items = items::Model::list(&boot.app_context.db, Some(inf_filter)).await.unwrap();
+    let inf_items = items::Model::list(&boot.app_context.db, bag.id, Some(inf_filter)).await.unwrap();
 
     insta::with_settings!({
         filters => testing::CLEANUP_DATE.to_vec()
@@ -153,3 +150,180 @@ async fn can_filter() {
         ))
     });
 }
+
+#[tokio::test]
+#[serial]
+async fn can_search_ignoring_case_and_accents() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let items = [
+        ("Épée longue", Some("Arme tranchante")),
+        ("Potion", Some("Soigne les blessures LÉGÈRES")),
+        ("Arc", Some("Bois 100% naturel")),
+        ("Torche", None),
+    ];
+    for (name, description) in items {
+        items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+            name: name.to_string(),
+            description: description.map(ToString::to_string),
+            ..Default::default()
+        }).await.unwrap();
+    }
+
+    let search = |filter: interface::ItemFilter| {
+        let db = boot.app_context.db.clone();
+        async move {
+            let mut names: Vec<String> = items::Model::list(&db, bag.id, Some(filter))
+                .await
+                .unwrap()
+                .items
+                .into_iter()
+                .map(|item| item.name)
+                .collect();
+            names.sort();
+            names
+        }
+    };
+
+    let text = |q: &str| interface::ItemFilter { q: Some(q.to_string()), ..Default::default() };
+    assert_eq!(search(text("EPEE")).await, ["Épée longue"]);
+    assert_eq!(search(text("légères")).await, ["Potion"]);
+    assert_eq!(search(text("ar")).await, ["Arc", "Épée longue"]);
+    assert_eq!(search(text("100%")).await, ["Arc"]);
+    assert!(search(text("_")).await.is_empty());
+
+    let name = |name: &str| interface::ItemFilter { name: Some(name.to_string()), ..Default::default() };
+    assert_eq!(search(name("epée")).await, ["Épée longue"]);
+    assert_eq!(search(name("t")).await, ["Torche"]);
+    assert_eq!(search(name("%e")).await, ["Torche", "Épée longue"]);
+
+    let description = interface::ItemFilter { description: Some("soigne".to_string()), ..Default::default() };
+    assert_eq!(search(description).await, ["Potion"]);
+
+    // updates keep the search columns in sync
+    let potion = items::Model::list(&boot.app_context.db, bag.id, Some(name("potion"))).await.unwrap().items.remove(0);
+    items::Model::update(&boot.app_context.db, Actor::System, bag.id, potion.id, None, interface::CreateUpdateItem {
+        name: "Élixir".to_string(),
+        ..Default::default()
+    }).await.unwrap();
+    assert_eq!(search(text("elixir")).await, ["Élixir"]);
+    assert!(search(text("legeres")).await.is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn can_archive_restore_and_purge() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let item = items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Test item".to_string(),
+        quantity: 2,
+        tags: vec!["tool".to_string()],
+        ..Default::default()
+    }).await.unwrap();
+    taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+    taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+
+    items::Model::delete(&boot.app_context.db, Actor::System, bag.id, item.id).await.unwrap();
+
+    // hidden from the bag and from draws, but the history is kept
+    assert!(matches!(
+        items::Model::find_by_id(&boot.app_context.db, bag.id, item.id).await,
+        Err(ModelError::EntityNotFound)
+    ));
+    let listed = items::Model::list(&boot.app_context.db, bag.id, None).await.unwrap();
+    assert_eq!(listed.total_results, 0);
+    let archived = items::Model::list(&boot.app_context.db, bag.id, Some(interface::ItemFilter {
+        archived: true,
+        ..Default::default()
+    })).await.unwrap();
+    assert_eq!(archived.items.len(), 1);
+    assert!(archived.items[0].deleted_at.is_some());
+    let drawn = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await;
+    assert!(matches!(drawn, Err(taken_items::DrawError::BagExhausted(_))));
+    let history = taken_items::Model::history(&boot.app_context.db, bag.id, None).await.unwrap();
+    assert_eq!(history.taken_history[0].item.id, item.id);
+
+    let restored = items::Model::restore(&boot.app_context.db, Actor::System, bag.id, item.id).await.unwrap();
+    assert!(restored.deleted_at.is_none());
+    assert_eq!(restored.into_item(&boot.app_context.db).await.unwrap().tags, ["tool"]);
+
+    // only items archived before the cutoff are purged
+    items::Model::delete(&boot.app_context.db, Actor::System, bag.id, item.id).await.unwrap();
+    let long_ago = chrono::Utc::now().naive_utc() - chrono::Duration::days(1);
+    assert_eq!(items::Model::purge(&boot.app_context.db, Actor::System, long_ago).await.unwrap(), 0);
+    let soon = chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+    assert_eq!(items::Model::purge(&boot.app_context.db, Actor::System, soon).await.unwrap(), 1);
+    let history = taken_items::Model::history(&boot.app_context.db, bag.id, None).await.unwrap();
+    assert_eq!(history.total_results, 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn items_show_remaining_and_draw_stats() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let rope = items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Rope".to_string(),
+        quantity: 2,
+        size: interface::ItemSize::Medium,
+        ..Default::default()
+    }).await.unwrap();
+    let fresh = rope.clone().into_item(&boot.app_context.db).await.unwrap();
+    assert_eq!((fresh.remaining, fresh.times_drawn, fresh.last_drawn_at), (Some(2), 0, None));
+
+    for _ in 0..2 {
+        taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+        taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    }
+    let torch = items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Torch".to_string(),
+        infinite: true,
+        ..Default::default()
+    }).await.unwrap();
+
+    let drawn = items::Model::find_by_id(&boot.app_context.db, bag.id, rope.id).await.unwrap()
+        .into_item(&boot.app_context.db).await.unwrap();
+    assert_eq!(drawn.remaining, Some(0));
+    assert_eq!(drawn.times_drawn, 2);
+    assert!(drawn.last_drawn_at.is_some());
+
+    let listed = items::Model::list(&boot.app_context.db, bag.id, None).await.unwrap();
+    let stats: Vec<_> = listed.items.iter().map(|item| (item.name.as_str(), item.remaining, item.times_drawn)).collect();
+    assert_eq!(stats, [("Torch", None, 0), ("Rope", Some(0), 2)]);
+
+    let available = items::Model::list(&boot.app_context.db, bag.id, Some(interface::ItemFilter {
+        available: true,
+        ..Default::default()
+    })).await.unwrap();
+    assert_eq!(available.items.iter().map(|item| item.id).collect::<Vec<_>>(), [torch.id]);
+    assert_eq!(available.facets.infinite.len(), 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn rounds_must_be_a_valid_range() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let item = |rounds| interface::CreateUpdateItem {
+        name: "Timed item".to_string(),
+        size: interface::ItemSize::Medium,
+        rounds,
+        ..Default::default()
+    };
+
+    for invalid in [interface::RoundRange { min: 0, max: 3 }, interface::RoundRange { min: 4, max: 2 }] {
+        let created = items::Model::create(&boot.app_context.db, Actor::System, bag.id, item(Some(invalid))).await;
+        assert!(created.is_err(), "{invalid:?} was accepted");
+    }
+
+    let range = interface::RoundRange { min: 2, max: 5 };
+    let created = items::Model::create(&boot.app_context.db, Actor::System, bag.id, item(Some(range))).await.unwrap();
+    assert_eq!(created.rounds(), Some(range));
+    let found = items::Model::find_by_id(&boot.app_context.db, bag.id, created.id).await.unwrap();
+    assert_eq!(found.rounds(), Some(range));
+}
@@ -1,3 +1,11 @@
+mod prepare_data;
+#[allow(unused_imports)]
 mod users;
+mod bags;
 mod items;
-mod taken_items;
\ No newline at end of file
+mod taken_items;
+mod draw;
+mod dice;
+mod inventory_entries;
+mod game_rounds;
+mod draw_transitions;
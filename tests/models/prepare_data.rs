@@ -0,0 +1,26 @@
+use loco_rs::testing;
+use roadiebag2::{app::App, models::bags};
+use sea_orm::DatabaseConnection;
+
+/// How many changes to the draws of a bag can be undone by default
+pub const HISTORY_DEPTH: u64 = 20;
+
+/// Seeds the fixture users and creates an empty bag owned by the first one.
+pub async fn init_bag(db: &DatabaseConnection) -> bags::Model {
+    testing::seed::<App>(db).await.unwrap();
+
+    bags::Model::create(
+        db,
+        1,
+        interface::CreateUpdateBag {
+            name: "Test bag".to_string(),
+            draw_strategy: None,
+            auto_restock: false,
+            rounds_dice: None,
+            hand_size: None,
+            reroll_budget: None,
+        },
+    )
+    .await
+    .unwrap()
+}
@@ -1,10 +1,11 @@
 use insta::assert_debug_snapshot;
 use roadiebag2::app::App;
-use loco_rs::testing;
+use loco_rs::{model::ModelError, testing};
 use serial_test::serial;
-use test_log::test;
 use tracing_test::traced_test;
-use roadiebag2::models::{items, taken_items};
+use roadiebag2::draw;
+use roadiebag2::models::{audit_events::Actor, bags, game_rounds, items, taken_items};
+use super::prepare_data::{self, HISTORY_DEPTH};
 
 macro_rules! configure_insta {
     ($($expr:expr),*) => {
@@ -21,25 +22,23 @@ async fn test_model() {
     configure_insta!();
 
     let boot = testing::boot_test::<App>().await.unwrap();
-    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
 
     let create = interface::CreateUpdateItem {
         name: "Test item".to_string(),
-        description: None,
         quantity: 2,
-        size: interface::ItemSize::Small,
-        infinite: false
+        ..Default::default()
     };
 
-    let model = items::Model::create(&boot.app_context.db, create).await;
+    items::Model::create(&boot.app_context.db, Actor::System, bag.id, create).await.unwrap();
 
-    let current_taken_none = taken_items::Model::get_current(&boot.app_context.db).await.unwrap();
+    let current_taken_none = taken_items::Model::get_current(&boot.app_context.db, bag.id).await.unwrap();
 
-    let current_random = taken_items::Model::get_random(&boot.app_context.db).await.unwrap();
+    let current_random = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
 
-    taken_items::Model::mark_done(&boot.app_context.db).await.unwrap();
+    taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
 
-    let current_taken_done = taken_items::Model::get_current(&boot.app_context.db).await.unwrap();
+    let current_taken_done = taken_items::Model::get_current(&boot.app_context.db, bag.id).await.unwrap();
 
 
     insta::with_settings!({
@@ -66,21 +65,351 @@ async fn test_decrement() {
     configure_insta!();
 
     let boot = testing::boot_test::<App>().await.unwrap();
-    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
 
     let create = interface::CreateUpdateItem {
         name: "Test item".to_string(),
-        description: None,
         quantity: 2,
-        size: interface::ItemSize::Small,
-        infinite: false
+        ..Default::default()
     };
 
-    let _model = items::Model::create(&boot.app_context.db, create).await;
-    let current_random = taken_items::Model::get_random(&boot.app_context.db).await.unwrap();
+    let _model = items::Model::create(&boot.app_context.db, Actor::System, bag.id, create).await;
+    let current_random = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
 
-    let decr = taken_items::Model::decrement_rounds(&boot.app_context.db).await.unwrap().unwrap();
+    let decr = taken_items::Model::decrement_rounds(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap().unwrap();
 
     assert_eq!(current_random.rounds_left - 1, decr.rounds_left)
 }
 
+
+#[tokio::test]
+#[serial]
+async fn weighted_draws_favor_heavy_items() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let rare = items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Rare item".to_string(),
+        infinite: true,
+        ..Default::default()
+    }).await.unwrap();
+    items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Common item".to_string(),
+        infinite: true,
+        weight: 9,
+        ..Default::default()
+    }).await.unwrap();
+
+    let mut rare_draws = 0;
+    for _ in 0..100 {
+        let drawn = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+        if drawn.item_id == rare.id {
+            rare_draws += 1;
+        }
+        taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    }
+
+    // expected 10 rare draws out of 100; 30 is more than five standard deviations away
+    assert!(rare_draws < 30, "rare item drawn {rare_draws} times out of 100");
+}
+
+#[tokio::test]
+#[serial]
+async fn exhausted_bag_is_an_error() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+
+    let empty = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await;
+    assert!(matches!(empty, Err(taken_items::DrawError::BagExhausted(id)) if id == bag.id));
+
+    items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Test item".to_string(),
+        ..Default::default()
+    }).await.unwrap();
+    taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+    taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+
+    let used_up = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await;
+    assert!(matches!(used_up, Err(taken_items::DrawError::BagExhausted(_))));
+}
+
+#[tokio::test]
+#[serial]
+async fn auto_restock_refills_exhausted_bag() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+    let bag = bags::Model::update(&boot.app_context.db, bag.id, interface::CreateUpdateBag {
+        name: bag.name,
+        draw_strategy: None,
+        auto_restock: true,
+        rounds_dice: None,
+        hand_size: None,
+        reroll_budget: None,
+    }).await.unwrap();
+
+    let item = items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Test item".to_string(),
+        quantity: 2,
+        ..Default::default()
+    }).await.unwrap();
+
+    for _ in 0..5 {
+        let drawn = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+        assert_eq!(drawn.item_id, item.id);
+        taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn auto_restock_leaves_out_items_in_hand() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+    let bag = bags::Model::update(&boot.app_context.db, bag.id, interface::CreateUpdateBag {
+        name: bag.name,
+        draw_strategy: None,
+        auto_restock: true,
+        rounds_dice: None,
+        hand_size: None,
+        reroll_budget: None,
+    }).await.unwrap();
+
+    let item = items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+        name: "Test item".to_string(),
+        ..Default::default()
+    }).await.unwrap();
+
+    let (rounds, tags) = (draw::RoundsBySize::default(), interface::TagFilter::default());
+    let draw = || taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &rounds, 2, &tags);
+    let drawn = draw().await.unwrap();
+    assert_eq!(drawn.item_id, item.id);
+
+    // its only unit is in the hand, so restocking finds nothing to refill
+    assert!(matches!(draw().await, Err(taken_items::DrawError::BagExhausted(_))));
+    let hand: Vec<_> = taken_items::Model::get_hand(&boot.app_context.db, bag.id).await.unwrap().into_iter().map(|taken| taken.id).collect();
+    assert_eq!(hand, [drawn.id]);
+
+    taken_items::Model::mark_draw_done(&boot.app_context.db, Actor::System, bag.id, drawn.id, HISTORY_DEPTH).await.unwrap();
+    assert_eq!(draw().await.unwrap().item_id, item.id);
+}
+
+#[tokio::test]
+#[serial]
+async fn rounds_follow_the_item_then_its_size() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+    let rounds = draw::RoundsBySize {
+        small: interface::RoundRange { min: 1, max: 1 },
+        medium: interface::RoundRange { min: 2, max: 2 },
+        large: interface::RoundRange { min: 7, max: 9 },
+    };
+
+    let item = |name: &str, size, rounds| interface::CreateUpdateItem {
+        name: name.to_string(),
+        size,
+        rounds,
+        ..Default::default()
+    };
+    let fixed = items::Model::create(&boot.app_context.db, Actor::System, bag.id, item("Fixed", interface::ItemSize::Small, Some(interface::RoundRange { min: 3, max: 3 }))).await.unwrap();
+    let large = items::Model::create(&boot.app_context.db, Actor::System, bag.id, item("Large", interface::ItemSize::Large, None)).await.unwrap();
+
+    for _ in 0..2 {
+        let drawn = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Uniform, &rounds, 1, &interface::TagFilter::default()).await.unwrap();
+        if drawn.item_id == fixed.id {
+            assert_eq!(drawn.rounds_total, 3);
+        } else {
+            assert_eq!(drawn.item_id, large.id);
+            assert!((7..=9).contains(&drawn.rounds_total), "rolled {}", drawn.rounds_total);
+        }
+        assert_eq!(drawn.rounds_left, drawn.rounds_total);
+        taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn dice_rounds_are_recorded_on_the_draw() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+    let bag = bags::Model::update(&boot.app_context.db, bag.id, interface::CreateUpdateBag {
+        name: bag.name,
+        draw_strategy: None,
+        auto_restock: false,
+        rounds_dice: Some("10d1+2".to_string()),
+        hand_size: None,
+        reroll_budget: None,
+    }).await.unwrap();
+
+    let item = |name: &str, rounds, rounds_dice: Option<&str>| interface::CreateUpdateItem {
+        name: name.to_string(),
+        rounds,
+        rounds_dice: rounds_dice.map(ToString::to_string),
+        ..Default::default()
+    };
+    let own_dice = items::Model::create(&boot.app_context.db, Actor::System, bag.id, item("Own dice", Some(interface::RoundRange { min: 1, max: 1 }), Some("2d1 keep highest"))).await.unwrap();
+    let own_range = items::Model::create(&boot.app_context.db, Actor::System, bag.id, item("Own range", Some(interface::RoundRange { min: 3, max: 3 }), None)).await.unwrap();
+    let bag_dice = items::Model::create(&boot.app_context.db, Actor::System, bag.id, item("Bag dice", None, None)).await.unwrap();
+
+    for _ in 0..3 {
+        let drawn = taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Uniform, &draw::RoundsBySize::default(), 1, &interface::TagFilter::default()).await.unwrap();
+        let expected = if drawn.item_id == own_dice.id {
+            Some(interface::DiceRoll {
+                expression: "2d1kh1".to_string(),
+                dice: vec![interface::DiceRolls { dice: "2d1kh1".to_string(), rolls: vec![1, 1], kept: vec![1] }],
+                total: 1,
+            })
+        } else if drawn.item_id == own_range.id {
+            None
+        } else {
+            assert_eq!(drawn.item_id, bag_dice.id);
+            Some(interface::DiceRoll {
+                expression: "10d1+2".to_string(),
+                dice: vec![interface::DiceRolls { dice: "10d1".to_string(), rolls: vec![1; 10], kept: vec![1; 10] }],
+                total: 12,
+            })
+        };
+        assert_eq!(drawn.rounds_total, expected.as_ref().map_or(3, |roll| i16::try_from(roll.total).unwrap()));
+        assert_eq!(drawn.rounds_roll, expected);
+
+        let current = taken_items::Model::get_current(&boot.app_context.db, bag.id).await.unwrap().unwrap();
+        assert_eq!(current.rounds_roll, drawn.rounds_roll);
+        taken_items::Model::mark_done(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    }
+
+    let invalid = items::Model::create(&boot.app_context.db, Actor::System, bag.id, item("Bad dice", None, Some("2d"))).await;
+    assert!(invalid.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn hand_holds_several_active_draws() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let bag = prepare_data::init_bag(&boot.app_context.db).await;
+    for name in ["Torch", "Rope", "Rations", "Dagger"] {
+        items::Model::create(&boot.app_context.db, Actor::System, bag.id, interface::CreateUpdateItem {
+            name: name.to_string(),
+            quantity: 2,
+            rounds: Some(interface::RoundRange { min: 2, max: 2 }),
+            ..Default::default()
+        }).await.unwrap();
+    }
+
+    let (rounds, tags) = (draw::RoundsBySize::default(), interface::TagFilter::default());
+    let draw = || taken_items::Model::get_random(&boot.app_context.db, &bag, Actor::System, &draw::Weighted, &rounds, 3, &tags);
+    let mut drawn = vec![];
+    for _ in 0..3 {
+        drawn.push(draw().await.unwrap());
+    }
+    let hand = taken_items::Model::get_hand(&boot.app_context.db, bag.id).await.unwrap();
+    assert_eq!(hand, drawn);
+
+    // a full hand hands back its newest draw
+    assert_eq!(draw().await.unwrap(), drawn[2]);
+
+    let decremented = taken_items::Model::decrement_draw(&boot.app_context.db, Actor::System, bag.id, drawn[0].id, HISTORY_DEPTH).await.unwrap();
+    assert_eq!((decremented.rounds_left, decremented.status), (1, interface::TakenStatus::Active));
+    taken_items::Model::mark_draw_done(&boot.app_context.db, Actor::System, bag.id, drawn[1].id, HISTORY_DEPTH).await.unwrap();
+    let finished_again = taken_items::Model::mark_draw_done(&boot.app_context.db, Actor::System, bag.id, drawn[1].id, HISTORY_DEPTH).await;
+    assert!(matches!(finished_again, Err(ModelError::EntityNotFound)));
+
+    // the freed slot takes the next draw
+    let refill = draw().await.unwrap();
+    assert_ne!(refill.id, drawn[2].id);
+
+    let summary = game_rounds::Model::advance(&boot.app_context.db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    let expired: Vec<_> = summary.expired.iter().map(|expired| (expired.taken.id, expired.taken.status)).collect();
+    assert_eq!(expired, [(drawn[0].id, interface::TakenStatus::Done)]);
+    let rounds: Vec<_> = summary.active.iter().map(|taken| (taken.id, taken.rounds_left)).collect();
+    assert_eq!(rounds, [(refill.id, 1), (drawn[2].id, 1)]);
+    let hand: Vec<_> = taken_items::Model::get_hand(&boot.app_context.db, bag.id).await.unwrap().into_iter().map(|taken| taken.id).collect();
+    assert_eq!(hand, [refill.id, drawn[2].id]);
+}
+
+#[tokio::test]
+#[serial]
+async fn paused_draws_sit_out_rounds() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let bag = prepare_data::init_bag(db).await;
+    for name in ["Torch", "Rope"] {
+        items::Model::create(db, Actor::System, bag.id, interface::CreateUpdateItem {
+            name: name.to_string(),
+            rounds: Some(interface::RoundRange { min: 2, max: 2 }),
+            ..Default::default()
+        }).await.unwrap();
+    }
+    let (rounds, tags) = (draw::RoundsBySize::default(), interface::TagFilter::default());
+    let first = taken_items::Model::get_random(db, &bag, Actor::System, &draw::Weighted, &rounds, 2, &tags).await.unwrap();
+    let second = taken_items::Model::get_random(db, &bag, Actor::System, &draw::Weighted, &rounds, 2, &tags).await.unwrap();
+
+    let paused = taken_items::Model::pause(db, Actor::System, bag.id, second.id, HISTORY_DEPTH).await.unwrap();
+    assert_eq!(paused.status, interface::TakenStatus::Paused);
+    assert!(taken_items::Model::pause(db, Actor::System, bag.id, second.id, HISTORY_DEPTH).await.is_err());
+    assert!(taken_items::Model::decrement_draw(db, Actor::System, bag.id, second.id, HISTORY_DEPTH).await.is_err());
+
+    // the newest draw is paused, so the one before it loses the round
+    let decremented = taken_items::Model::decrement_rounds(db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap().unwrap();
+    assert_eq!((decremented.id, decremented.rounds_left), (first.id, 1));
+    let summary = game_rounds::Model::advance(db, Actor::System, bag.id, HISTORY_DEPTH).await.unwrap();
+    assert!(summary.active.is_empty());
+    assert_eq!(summary.expired.iter().map(|expired| expired.taken.id).collect::<Vec<_>>(), [first.id]);
+    let hand = taken_items::Model::get_hand(db, bag.id).await.unwrap();
+    assert_eq!(hand, [paused]);
+
+    // rounds can be added to a paused draw, and taking them all off ends it
+    let extended = taken_items::Model::adjust_rounds(db, Actor::System, bag.id, second.id, interface::AdjustRounds { delta: 3 }, HISTORY_DEPTH).await.unwrap();
+    assert_eq!((extended.rounds_left, extended.status), (5, interface::TakenStatus::Paused));
+    assert!(matches!(
+        taken_items::Model::adjust_rounds(db, Actor::System, bag.id, second.id, interface::AdjustRounds { delta: 0 }, HISTORY_DEPTH).await,
+        Err(taken_items::RoundsError::InvalidAmount(0))
+    ));
+    let resumed = taken_items::Model::resume(db, Actor::System, bag.id, second.id, HISTORY_DEPTH).await.unwrap();
+    assert_eq!(resumed.status, interface::TakenStatus::Active);
+    let ended = taken_items::Model::adjust_rounds(db, Actor::System, bag.id, second.id, interface::AdjustRounds { delta: -10 }, HISTORY_DEPTH).await.unwrap();
+    assert_eq!((ended.rounds_left, ended.status), (0, interface::TakenStatus::Done));
+}
+
+#[tokio::test]
+#[serial]
+async fn rerolls_put_the_item_back() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let bag = prepare_data::init_bag(db).await;
+    for name in ["Torch", "Rope"] {
+        items::Model::create(db, Actor::System, bag.id, interface::CreateUpdateItem {
+            name: name.to_string(),
+            ..Default::default()
+        }).await.unwrap();
+    }
+    let (rounds, tags) = (draw::RoundsBySize::default(), interface::TagFilter::default());
+    let drawn = taken_items::Model::get_random(db, &bag, Actor::System, &draw::Weighted, &rounds, 1, &tags).await.unwrap();
+
+    let rerolled = taken_items::Model::reroll(db, &bag, Actor::System, drawn.id, &draw::Weighted, &rounds, Some(1), &tags).await.unwrap();
+    assert_ne!(rerolled.id, drawn.id);
+    assert_eq!(rerolled.status, interface::TakenStatus::Active);
+    let history = taken_items::Model::history(db, bag.id, None).await.unwrap();
+    let statuses: Vec<_> = history.taken_history.iter().map(|entry| (entry.taken.id, entry.taken.status)).collect();
+    assert_eq!(statuses, [(rerolled.id, interface::TakenStatus::Active), (drawn.id, interface::TakenStatus::Rerolled)]);
+    let hand: Vec<_> = taken_items::Model::get_hand(db, bag.id).await.unwrap().into_iter().map(|taken| taken.id).collect();
+    assert_eq!(hand, [rerolled.id]);
+
+    assert!(matches!(
+        taken_items::Model::reroll(db, &bag, Actor::System, rerolled.id, &draw::Weighted, &rounds, Some(1), &tags).await,
+        Err(taken_items::DrawError::RerollBudgetSpent(1))
+    ));
+    assert!(taken_items::Model::reroll(db, &bag, Actor::System, drawn.id, &draw::Weighted, &rounds, None, &tags).await.is_err());
+
+    // the budget lasts for the game, and starts over with the next one
+    game_rounds::Model::new_game(db, Actor::System, bag.id).await.unwrap();
+    let last = taken_items::Model::reroll(db, &bag, Actor::System, rerolled.id, &draw::Weighted, &rounds, Some(1), &tags).await.unwrap();
+
+    // rerolled draws did not use up either item
+    taken_items::Model::mark_draw_done(db, Actor::System, bag.id, last.id, HISTORY_DEPTH).await.unwrap();
+    let other = taken_items::Model::get_random(db, &bag, Actor::System, &draw::Weighted, &rounds, 1, &tags).await.unwrap();
+    assert_ne!(other.item_id, last.item_id);
+    taken_items::Model::mark_draw_done(db, Actor::System, bag.id, other.id, HISTORY_DEPTH).await.unwrap();
+    assert!(matches!(
+        taken_items::Model::get_random(db, &bag, Actor::System, &draw::Weighted, &rounds, 1, &tags).await,
+        Err(taken_items::DrawError::BagExhausted(_))
+    ));
+}
@@ -0,0 +1,105 @@
+use roadiebag2::app::App;
+use loco_rs::testing;
+use serial_test::serial;
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn records_item_and_draw_changes() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &owner).await;
+        let player = prepare_data::join_bag(&request, &ctx, &owner, &bag, "player@loco.com", interface::BagRole::Player).await;
+        let (owner_key, owner_value) = prepare_data::auth_header(&owner.token);
+        let (player_key, player_value) = prepare_data::auth_header(&player.token);
+
+        let mut create = interface::CreateUpdateItem {
+            name: "Torch".to_string(),
+            quantity: 2,
+            ..Default::default()
+        };
+        let item: interface::Item = request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&create)
+            .add_header(owner_key.clone(), owner_value.clone())
+            .await
+            .json();
+        create.quantity = 3;
+        request
+            .post(&format!("/api/bags/{}/items/{}", bag.id, item.id))
+            .json(&create)
+            .add_header(owner_key.clone(), owner_value.clone())
+            .await
+            .assert_status_ok();
+
+        let taken: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(player_key.clone(), player_value.clone())
+            .await
+            .json();
+        request
+            .post(&format!("/api/bags/{}/taken/done", bag.id))
+            .add_header(player_key.clone(), player_value.clone())
+            .await
+            .assert_status_ok();
+
+        let response = request
+            .get("/api/audit")
+            .add_header(owner_key.clone(), owner_value.clone())
+            .await;
+        response.assert_status_ok();
+        let page: interface::AuditPage = response.json();
+        let actions: Vec<_> = page.events.iter().map(|event| event.action).collect();
+        assert_eq!(actions, vec![
+            interface::AuditAction::Done,
+            interface::AuditAction::Draw,
+            interface::AuditAction::Update,
+            interface::AuditAction::Create,
+        ]);
+
+        let update = &page.events[2];
+        assert_eq!(update.actor_pid, Some(owner.user.pid.to_string()));
+        assert_eq!(update.entity, interface::AuditEntity::Item);
+        assert_eq!(update.entity_id, item.id);
+        assert_eq!(update.before.as_ref().unwrap()["quantity"], 2);
+        assert_eq!(update.after.as_ref().unwrap()["quantity"], 3);
+
+        let done = &page.events[0];
+        assert_eq!(done.actor_pid, Some(player.user.pid.to_string()));
+        assert_eq!(done.entity_id, taken.id);
+        assert_eq!(done.before.as_ref().unwrap()["done"], false);
+        assert_eq!(done.after.as_ref().unwrap()["done"], true);
+
+        let by_player: interface::AuditPage = request
+            .get("/api/audit")
+            .add_query_params(interface::AuditFilter {
+                actor: Some(player.user.pid.to_string()),
+                entity: Some(interface::AuditEntity::TakenItem),
+                ..Default::default()
+            })
+            .add_header(owner_key.clone(), owner_value.clone())
+            .await
+            .json();
+        assert_eq!(by_player.total_results, 2);
+
+        let before_all: interface::AuditPage = request
+            .get("/api/audit")
+            .add_query_params(interface::AuditFilter {
+                to: Some(page.events[3].created_at),
+                ..Default::default()
+            })
+            .add_header(owner_key, owner_value)
+            .await
+            .json();
+        assert_eq!(before_all.total_results, 0);
+
+        // players do not run the bag, so they see none of its changes
+        let for_player: interface::AuditPage = request
+            .get("/api/audit")
+            .add_header(player_key, player_value)
+            .await
+            .json();
+        assert_eq!(for_player.total_results, 0);
+    })
+    .await;
+}
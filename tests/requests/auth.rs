@@ -4,8 +4,6 @@ use roadiebag2::{app::App, models::users};
 use rstest::rstest;
 use serial_test::serial;
 
-use super::prepare_data;
-
 // TODO: see how to dedup / extract this to app-local test utils
 // not to framework, because that would require a runtime dep on insta
 macro_rules! configure_insta {
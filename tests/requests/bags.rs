@@ -0,0 +1,123 @@
+use insta::assert_debug_snapshot;
+use roadiebag2::app::App;
+use roadiebag2::models::bags;
+use loco_rs::testing;
+use serial_test::serial;
+use super::prepare_data;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("bag_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn bag_crud() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let create = interface::CreateUpdateBag {
+            name: "Dungeon crawl".to_string(),
+            draw_strategy: None,
+            auto_restock: false,
+            rounds_dice: None,
+            hand_size: None,
+            reroll_budget: None,
+        };
+        let create_response = request
+            .post("/api/bags")
+            .json(&create)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        create_response.assert_status_ok();
+        let bag: interface::Bag = create_response.json();
+
+        let owned = bags::Model::find_for_user(&ctx.db, bag.id, user.user.id).await;
+        assert!(owned.is_ok());
+
+        let update_response = request
+            .post(&format!("/api/bags/{}", bag.id))
+            .json(&interface::CreateUpdateBag {
+                name: "Overland travel".to_string(),
+                draw_strategy: Some(interface::DrawStrategyKind::ShuffleBag),
+                auto_restock: false,
+                rounds_dice: None,
+                hand_size: None,
+                reroll_budget: None,
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        update_response.assert_status_ok();
+
+        let list_response = request
+            .get("/api/bags")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        list_response.assert_status_ok();
+
+        let delete_response = request
+            .delete(&format!("/api/bags/{}", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        delete_response.assert_status_ok();
+
+        let read_response = request
+            .get(&format!("/api/bags/{}", bag.id))
+            .add_header(auth_key, auth_value)
+            .await;
+
+        insta::with_settings!({
+            filters => testing::CLEANUP_DATE.to_vec()
+        }, {
+            assert_debug_snapshot!((
+                (create_response.status_code(), create_response.text()),
+                (update_response.status_code(), update_response.text()),
+                (list_response.status_code(), list_response.text()),
+                delete_response.status_code(),
+                (read_response.status_code(), read_response.text())
+            ))
+        });
+    })
+        .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_access_other_users_bag() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &owner).await;
+
+        let other = prepare_data::init_user_login_as(&request, &ctx, "other@loco.com").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&other.token);
+
+        let items_response = request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&interface::CreateUpdateItem {
+                name: "Test item".to_string(),
+                ..Default::default()
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+
+        let taken_response = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(auth_key, auth_value)
+            .await;
+
+        assert_debug_snapshot!((
+            (items_response.status_code(), items_response.text()),
+            (taken_response.status_code(), taken_response.text())
+        ));
+    })
+        .await;
+}
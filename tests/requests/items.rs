@@ -1,4 +1,5 @@
-use insta::{assert_debug_snapshot, with_settings};
+use axum::http::{HeaderName, HeaderValue};
+use insta::assert_debug_snapshot;
 use roadiebag2::app::App;
 use loco_rs::testing;
 use serial_test::serial;
@@ -20,18 +21,17 @@ async fn item_crud() {
 
     testing::request::<App, _, _>(|request, ctx| async move {
         let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
 
         let create = interface::CreateUpdateItem {
             name: "Test item".to_string(),
-            description: None,
             quantity: 2,
-            size: interface::ItemSize::Small,
-            infinite: false
+            ..Default::default()
         };
 
         let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
         let create_response = request
-            .post("/api/items")
+            .post(&format!("/api/bags/{}/items", bag.id))
             .json(&create)
             .add_header(auth_key.clone(), auth_value.clone())
             .await;
@@ -40,7 +40,7 @@ async fn item_crud() {
 
 
         let read_response = request
-            .get(&format!("/api/items/{}", model.id))
+            .get(&format!("/api/bags/{}/items/{}", bag.id, model.id))
             .add_header(auth_key.clone(), auth_value.clone())
             .await;
         read_response.assert_status_ok();
@@ -51,18 +51,36 @@ async fn item_crud() {
         };
 
         let update_response = request
-            .post(&format!("/api/items/{}", model.id))
+            .post(&format!("/api/bags/{}/items/{}", bag.id, model.id))
             .json(&update)
             .add_header(auth_key.clone(), auth_value.clone())
             .await;
         update_response.assert_status_ok();
 
         let delete_response = request
-            .delete(&format!("/api/items/{}", model.id))
-            .add_header(auth_key, auth_value)
+            .delete(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .add_header(auth_key.clone(), auth_value.clone())
             .await;
         delete_response.assert_status_ok();
 
+        // archived items are gone until restored
+        request
+            .get(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_not_ok();
+        let restored: interface::Item = request
+            .post(&format!("/api/bags/{}/items/{}/restore", bag.id, model.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(restored.quantity, 4);
+        request
+            .get(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .add_header(auth_key, auth_value)
+            .await
+            .assert_status_ok();
+
         insta::with_settings!({
             filters => testing::CLEANUP_DATE.to_vec()
             }, {
@@ -80,3 +98,435 @@ async fn item_crud() {
     })
         .await;
 }
+
+#[tokio::test]
+#[serial]
+async fn update_checks_if_match() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let create = interface::CreateUpdateItem {
+            name: "Lantern".to_string(),
+            size: interface::ItemSize::Medium,
+            ..Default::default()
+        };
+        let model: interface::Item = request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&create)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+
+        let read_response = request
+            .get(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let etag = read_response.header("etag");
+        assert_eq!(etag, "\"1\"");
+
+        // the first editor saw the current version
+        let first = request
+            .post(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&interface::CreateUpdateItem { quantity: 2, ..create.clone() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_header(HeaderName::from_static("if-match"), etag.clone())
+            .await;
+        first.assert_status_ok();
+        assert_eq!(first.header("etag"), "\"2\"");
+        assert_eq!(first.json::<interface::Item>().version, 2);
+
+        // the second one did not
+        let second = request
+            .post(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&interface::CreateUpdateItem { quantity: 5, ..create.clone() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_header(HeaderName::from_static("if-match"), etag)
+            .await;
+        assert_eq!(second.status_code(), 412);
+        assert_eq!(second.json::<interface::VersionMismatch>(), interface::VersionMismatch {
+            error: "version_mismatch".to_string(),
+            item_id: model.id,
+            current_version: 2,
+            description: "The item changed since it was last read".to_string(),
+        });
+
+        // without If-Match, or with `*`, the update always goes through
+        request
+            .post(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&interface::CreateUpdateItem { quantity: 5, ..create.clone() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_header(HeaderName::from_static("if-match"), HeaderValue::from_static("*"))
+            .await
+            .assert_status_ok();
+        let item: interface::Item = request
+            .post(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&create)
+            .add_header(auth_key, auth_value)
+            .await
+            .json();
+        assert_eq!(item.quantity, 1);
+        assert_eq!(item.version, 4);
+    })
+        .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn update_reads_if_match_lists_and_weak_tags() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let create = interface::CreateUpdateItem {
+            name: "Lantern".to_string(),
+            ..Default::default()
+        };
+        let model: interface::Item = request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&create)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+
+        // weak tags never match, even the current version
+        let weak = request
+            .post(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&create)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_header(HeaderName::from_static("if-match"), HeaderValue::from_static("W/\"1\""))
+            .await;
+        assert_eq!(weak.status_code(), 412);
+
+        // a list matches when any of its tags does
+        let listed = request
+            .post(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&create)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_header(HeaderName::from_static("if-match"), HeaderValue::from_static("\"7\", W/\"2\",\"1\""))
+            .await;
+        listed.assert_status_ok();
+        assert_eq!(listed.header("etag"), "\"2\"");
+
+        let unlisted = request
+            .patch(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&serde_json::json!({ "quantity": 3 }))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_header(HeaderName::from_static("if-match"), HeaderValue::from_static("\"1\", \"a,2\""))
+            .await;
+        assert_eq!(unlisted.status_code(), 412);
+
+        for malformed in ["2", "\"2", "\"2\" \"3\"", ","] {
+            let response = request
+                .post(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+                .json(&create)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(HeaderName::from_static("if-match"), HeaderValue::from_static(malformed))
+                .await;
+            assert_eq!(response.status_code(), 400, "{malformed}");
+        }
+    })
+        .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn patch_changes_only_given_fields() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let model: interface::Item = request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&interface::CreateUpdateItem {
+                name: "Rope".to_string(),
+                description: Some("Fifty feet".to_string()),
+                quantity: 2,
+                size: interface::ItemSize::Medium,
+                weight: 3,
+                tags: vec!["tool".to_string()],
+                ..Default::default()
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+
+        let patched: interface::Item = request
+            .patch(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&serde_json::json!({ "description": "Thirty feet" }))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(patched.description.as_deref(), Some("Thirty feet"));
+        assert_eq!(patched.name, "Rope");
+        assert_eq!(patched.quantity, 2);
+        assert_eq!(patched.size, interface::ItemSize::Medium);
+        assert_eq!(patched.weight, 3);
+        assert_eq!(patched.tags, vec!["tool".to_string()]);
+        assert_eq!(patched.version, 2);
+
+        let cleared: interface::Item = request
+            .patch(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&serde_json::json!({ "description": null, "quantity": 5 }))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(cleared.description, None);
+        assert_eq!(cleared.quantity, 5);
+
+        // the merged item is validated like a full update
+        request
+            .patch(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&interface::PatchItem { quantity: Some(0), ..Default::default() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_not_ok();
+
+        let stale = request
+            .patch(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .json(&interface::PatchItem { weight: Some(1), ..Default::default() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_header(HeaderName::from_static("if-match"), HeaderValue::from_static("\"2\""))
+            .await;
+        assert_eq!(stale.status_code(), 412);
+
+        let item: interface::Item = request
+            .get(&format!("/api/bags/{}/items/{}", bag.id, model.id))
+            .add_header(auth_key, auth_value)
+            .await
+            .json();
+        assert_eq!(item.quantity, 5);
+        assert_eq!(item.weight, 3);
+        assert_eq!(item.version, 3);
+    })
+        .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn restock_and_adjust_stock() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let model: interface::Item = request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&interface::CreateUpdateItem {
+                name: "Potion".to_string(),
+                quantity: 3,
+                ..Default::default()
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+
+        // the potion in hand is not missing, so restocking leaves it out
+        request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+        let refilled: interface::Item = request
+            .post(&format!("/api/bags/{}/items/{}/restock", bag.id, model.id))
+            .json(&interface::Restock { amount: None, reason: None })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(refilled.remaining, Some(2));
+
+        let adjusted: interface::Item = request
+            .post(&format!("/api/bags/{}/items/{}/adjust", bag.id, model.id))
+            .json(&interface::AdjustStock { delta: -1, reason: "drunk during the night".to_string() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(adjusted.remaining, Some(1));
+
+        let too_many = request
+            .post(&format!("/api/bags/{}/items/{}/adjust", bag.id, model.id))
+            .json(&interface::AdjustStock { delta: -2, reason: "spilled".to_string() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(too_many.status_code(), 422);
+
+        let restocked: interface::Item = request
+            .post(&format!("/api/bags/{}/items/{}/restock", bag.id, model.id))
+            .json(&interface::Restock { amount: Some(5), reason: Some("found a stash".to_string()) })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(restocked.remaining, Some(6));
+
+        let ledger: Vec<interface::InventoryEntry> = request
+            .get(&format!("/api/bags/{}/items/{}/inventory", bag.id, model.id))
+            .add_header(auth_key, auth_value)
+            .await
+            .json();
+        let entries: Vec<_> = ledger.iter().map(|entry| (entry.kind, entry.delta, entry.reason.as_deref())).collect();
+        assert_eq!(entries, [
+            (interface::InventoryKind::Restock, 5, Some("found a stash")),
+            (interface::InventoryKind::Remove, -1, Some("drunk during the night")),
+            (interface::InventoryKind::Draw, -1, None),
+            (interface::InventoryKind::Add, 3, None),
+        ]);
+    })
+        .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn list_items_sorted_with_facets() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let items = [
+            ("Rope", 3, interface::ItemSize::Medium, false),
+            ("Arrows", 20, interface::ItemSize::Small, false),
+            ("Torch", 1, interface::ItemSize::Small, true),
+        ];
+        for (name, quantity, size, infinite) in items {
+            request
+                .post(&format!("/api/bags/{}/items", bag.id))
+                .json(&interface::CreateUpdateItem {
+                    name: name.to_string(),
+                    quantity,
+                    size,
+                    infinite,
+                    ..Default::default()
+                })
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status_ok();
+        }
+
+        // draw once, so exactly one item has been drawn
+        let drawn: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+
+        let list = |sort: &'static str, order: &'static str| request
+            .get(&format!("/api/bags/{}/items", bag.id))
+            .add_query_param("sort", sort)
+            .add_query_param("order", order)
+            .add_header(auth_key.clone(), auth_value.clone());
+        let names = |page: &interface::ItemPage| page.items.iter().map(|item| item.name.clone()).collect::<Vec<_>>();
+
+        let by_name: interface::ItemPage = list("Name", "Asc").await.json();
+        assert_eq!(names(&by_name), ["Arrows", "Rope", "Torch"]);
+
+        let by_quantity: interface::ItemPage = list("Quantity", "Desc").await.json();
+        assert_eq!(names(&by_quantity), ["Arrows", "Rope", "Torch"]);
+
+        let by_created: interface::ItemPage = list("CreatedAt", "Asc").await.json();
+        assert_eq!(names(&by_created), ["Rope", "Arrows", "Torch"]);
+
+        let by_times_drawn: interface::ItemPage = list("TimesDrawn", "Desc").await.json();
+        assert_eq!(by_times_drawn.items[0].id, drawn.item_id);
+        assert_eq!(by_times_drawn.total_results, 3);
+
+        let small: interface::ItemPage = request
+            .get(&format!("/api/bags/{}/items", bag.id))
+            .add_query_param("size", "Small")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(small.total_results, 2);
+
+        assert_debug_snapshot!((by_name.facets, small.facets));
+    }).await;
+}
+
+#[tokio::test]
+#[serial]
+async fn import_and_export_items() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let import = |format: &'static str, body: &'static str| request
+            .post(&format!("/api/bags/{}/items/import", bag.id))
+            .add_query_param("format", format)
+            .text(body)
+            .add_header(auth_key.clone(), auth_value.clone());
+        let csv = "name,description,quantity,size,infinite,weight,tags\n\
+                   Rope,,3,Medium,false,1,\"tool,climbing\"\n\
+                   Arrows,Sharp,20,Small,false,2,weapon\n";
+
+        // one bad row rejects the whole file
+        let rejected = import("Csv", "name,quantity,size,infinite\nRope,3,Medium,false\n,0,Small,false\nTorch,1,Huge,true\n").await;
+        rejected.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        let report: interface::ItemImportReport = rejected.json();
+        assert_eq!(report.errors.iter().map(|error| error.row).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(report.errors[0].errors.len(), 2);
+
+        let dry_run: interface::ItemImportReport = import("Csv", csv)
+            .add_query_param("dry_run", "true")
+            .await
+            .json();
+        assert_eq!((dry_run.created, dry_run.updated), (2, 0));
+
+        let page: interface::ItemPage = request
+            .get(&format!("/api/bags/{}/items", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(page.total_results, 0);
+
+        let imported: interface::ItemImportReport = import("Csv", csv).await.json();
+        assert_eq!((imported.created, imported.updated), (2, 0));
+
+        let upserted: interface::ItemImportReport = import("Yaml", "- name: Rope\n  quantity: 5\n  size: Medium\n  infinite: false\n- name: Torch\n  quantity: 1\n  size: Small\n  infinite: true\n")
+            .add_query_param("upsert", "true")
+            .await
+            .json();
+        assert_eq!((upserted.created, upserted.updated), (1, 1));
+
+        // an upsert names each item once
+        let repeated = import("Csv", "name,quantity,size,infinite\nLantern,1,Small,false\nRope,2,Medium,false\nLantern,2,Small,false\n")
+            .add_query_param("upsert", "true")
+            .await;
+        repeated.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        let report: interface::ItemImportReport = repeated.json();
+        assert_eq!(report.errors, [interface::ImportRowError {
+            row: 3,
+            errors: vec!["name: Lantern is already on row 1".to_string()],
+        }]);
+
+        let exported = request
+            .get(&format!("/api/bags/{}/items/export", bag.id))
+            .add_query_param("format", "Json")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        exported.assert_status_ok();
+        let items: Vec<interface::CreateUpdateItem> = exported.json();
+        let summary = items
+            .iter()
+            .map(|item| (item.name.as_str(), item.quantity, item.tags.join(",")))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, [
+            ("Rope", 5, String::new()),
+            ("Arrows", 20, "weapon".to_string()),
+            ("Torch", 1, String::new()),
+        ]);
+
+        let exported_csv = request
+            .get(&format!("/api/bags/{}/items/export", bag.id))
+            .add_query_param("format", "Csv")
+            .add_header(auth_key, auth_value)
+            .await
+            .text();
+        assert!(exported_csv.starts_with("name,description,quantity,size,infinite,weight,tags,min_rounds,max_rounds,rounds_dice\n"));
+        assert!(exported_csv.contains("Arrows,Sharp,20,Small,false,2,weapon,,,\n"));
+    }).await;
+}
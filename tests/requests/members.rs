@@ -0,0 +1,170 @@
+use insta::assert_debug_snapshot;
+use roadiebag2::app::App;
+use loco_rs::testing;
+use serial_test::serial;
+use super::prepare_data;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("member_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn invite_and_accept() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &owner).await;
+        let (owner_key, owner_value) = prepare_data::auth_header(&owner.token);
+
+        let invite_response = request
+            .post(&format!("/api/bags/{}/invites", bag.id))
+            .json(&interface::CreateInvite {
+                role: interface::BagRole::Player,
+            })
+            .add_header(owner_key.clone(), owner_value.clone())
+            .await;
+        invite_response.assert_status_ok();
+        let invite: interface::Invite = invite_response.json();
+
+        let player = prepare_data::init_user_login_as(&request, &ctx, "player@loco.com").await;
+        let (player_key, player_value) = prepare_data::auth_header(&player.token);
+
+        let accept_response = request
+            .post("/api/invites/accept")
+            .json(&interface::AcceptInvite {
+                token: invite.token.clone(),
+            })
+            .add_header(player_key.clone(), player_value.clone())
+            .await;
+        accept_response.assert_status_ok();
+
+        let reuse_response = request
+            .post("/api/invites/accept")
+            .json(&interface::AcceptInvite {
+                token: invite.token,
+            })
+            .add_header(player_key.clone(), player_value.clone())
+            .await;
+
+        let members_response = request
+            .get(&format!("/api/bags/{}/members", bag.id))
+            .add_header(player_key, player_value)
+            .await;
+        members_response.assert_status_ok();
+
+        insta::with_settings!({
+            filters => {
+                let mut fvec = testing::CLEANUP_DATE.to_vec();
+                fvec.extend(vec![
+                    (r"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}", "UUID")
+                ]);
+                fvec
+            }
+        }, {
+            assert_debug_snapshot!((
+                (accept_response.status_code(), accept_response.text()),
+                reuse_response.status_code(),
+                (members_response.status_code(), members_response.text())
+            ))
+        });
+    })
+        .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn roles_limit_actions() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &owner).await;
+        let (owner_key, owner_value) = prepare_data::auth_header(&owner.token);
+
+        let create = interface::CreateUpdateItem {
+            name: "Test item".to_string(),
+            quantity: 5,
+            ..Default::default()
+        };
+        request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&create)
+            .add_header(owner_key.clone(), owner_value.clone())
+            .await
+            .assert_status_ok();
+
+        let player = prepare_data::join_bag(&request, &ctx, &owner, &bag, "player@loco.com", interface::BagRole::Player).await;
+        let viewer = prepare_data::join_bag(&request, &ctx, &owner, &bag, "viewer@loco.com", interface::BagRole::Viewer).await;
+        let (player_key, player_value) = prepare_data::auth_header(&player.token);
+        let (viewer_key, viewer_value) = prepare_data::auth_header(&viewer.token);
+
+        let player_create = request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&create)
+            .add_header(player_key.clone(), player_value.clone())
+            .await;
+        let player_invite = request
+            .post(&format!("/api/bags/{}/invites", bag.id))
+            .json(&interface::CreateInvite {
+                role: interface::BagRole::Viewer,
+            })
+            .add_header(player_key.clone(), player_value.clone())
+            .await;
+        let player_draw = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(player_key.clone(), player_value.clone())
+            .await;
+        let player_decrement = request
+            .post(&format!("/api/bags/{}/taken/decrement", bag.id))
+            .add_header(player_key, player_value)
+            .await;
+
+        let viewer_read = request
+            .get(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(viewer_key.clone(), viewer_value.clone())
+            .await;
+        let viewer_draw = request
+            .post(&format!("/api/bags/{}/taken/done", bag.id))
+            .add_header(viewer_key.clone(), viewer_value.clone())
+            .await;
+        let viewer_delete = request
+            .delete(&format!("/api/bags/{}/items/1", bag.id))
+            .add_header(viewer_key, viewer_value)
+            .await;
+
+        let promote = request
+            .post(&format!("/api/bags/{}/members/{}", bag.id, player.user.pid))
+            .json(&interface::UpdateBagMember {
+                role: interface::BagRole::GameMaster,
+            })
+            .add_header(owner_key, owner_value)
+            .await;
+
+        let (gm_key, gm_value) = prepare_data::auth_header(&player.token);
+        let gm_create = request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&create)
+            .add_header(gm_key, gm_value)
+            .await;
+
+        assert_debug_snapshot!((
+            player_create.status_code(),
+            player_invite.status_code(),
+            player_draw.status_code(),
+            player_decrement.status_code(),
+            viewer_read.status_code(),
+            viewer_draw.status_code(),
+            viewer_delete.status_code(),
+            promote.status_code(),
+            gm_create.status_code()
+        ));
+    })
+        .await;
+}
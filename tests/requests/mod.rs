@@ -3,5 +3,10 @@ mod auth;
 mod prepare_data;
 mod user;
 
+pub mod audit;
+pub mod bags;
 pub mod items;
+pub mod members;
+pub mod rounds;
+pub mod tags;
 pub mod taken;
\ No newline at end of file
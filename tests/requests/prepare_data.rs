@@ -11,9 +11,13 @@ pub struct LoggedInUser {
 }
 
 pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedInUser {
+    init_user_login_as(request, ctx, USER_EMAIL).await
+}
+
+pub async fn init_user_login_as(request: &TestServer, ctx: &AppContext, email: &str) -> LoggedInUser {
     let register_payload = serde_json::json!({
         "name": "loco",
-        "email": USER_EMAIL,
+        "email": email,
         "password": USER_PASSWORD
     });
 
@@ -22,7 +26,7 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
         .post("/api/auth/register")
         .json(&register_payload)
         .await;
-    let user = users::Model::find_by_email(&ctx.db, USER_EMAIL)
+    let user = users::Model::find_by_email(&ctx.db, email)
         .await
         .unwrap();
 
@@ -35,7 +39,7 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
     let response = request
         .post("/api/auth/login")
         .json(&serde_json::json!({
-            "email": USER_EMAIL,
+            "email": email,
             "password": USER_PASSWORD
         }))
         .await;
@@ -43,7 +47,7 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
     let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
 
     LoggedInUser {
-        user: users::Model::find_by_email(&ctx.db, USER_EMAIL)
+        user: users::Model::find_by_email(&ctx.db, email)
             .await
             .unwrap(),
         token: login_response.token,
@@ -55,3 +59,49 @@ pub fn auth_header(token: &str) -> (HeaderName, HeaderValue) {
 
     (HeaderName::from_static("authorization"), auth_header_value)
 }
+
+pub async fn init_bag(request: &TestServer, user: &LoggedInUser) -> interface::Bag {
+    let (auth_key, auth_value) = auth_header(&user.token);
+    request
+        .post("/api/bags")
+        .json(&interface::CreateUpdateBag {
+            name: "Test bag".to_string(),
+            draw_strategy: None,
+            auto_restock: false,
+            rounds_dice: None,
+            hand_size: None,
+            reroll_budget: None,
+        })
+        .add_header(auth_key, auth_value)
+        .await
+        .json()
+}
+
+/// Registers a new user and has them join `bag` through an invite from `owner`.
+pub async fn join_bag(
+    request: &TestServer,
+    ctx: &AppContext,
+    owner: &LoggedInUser,
+    bag: &interface::Bag,
+    email: &str,
+    role: interface::BagRole,
+) -> LoggedInUser {
+    let (auth_key, auth_value) = auth_header(&owner.token);
+    let invite: interface::Invite = request
+        .post(&format!("/api/bags/{}/invites", bag.id))
+        .json(&interface::CreateInvite { role })
+        .add_header(auth_key, auth_value)
+        .await
+        .json();
+
+    let user = init_user_login_as(request, ctx, email).await;
+    let (auth_key, auth_value) = auth_header(&user.token);
+    request
+        .post("/api/invites/accept")
+        .json(&interface::AcceptInvite {
+            token: invite.token,
+        })
+        .add_header(auth_key, auth_value)
+        .await;
+    user
+}
@@ -0,0 +1,70 @@
+use loco_rs::testing;
+use roadiebag2::app::App;
+use serial_test::serial;
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn game_master_advances_rounds() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &owner).await;
+        let player = prepare_data::join_bag(&request, &ctx, &owner, &bag, "player@loco.com", interface::BagRole::Player).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&owner.token);
+
+        request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&interface::CreateUpdateItem {
+                name: "Torch".to_string(),
+                rounds: Some(interface::RoundRange { min: 1, max: 1 }),
+                ..Default::default()
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+        let taken: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+
+        let (player_key, player_value) = prepare_data::auth_header(&player.token);
+        let round: interface::GameRound = request
+            .get(&format!("/api/bags/{}/rounds", bag.id))
+            .add_header(player_key.clone(), player_value.clone())
+            .await
+            .json();
+        assert_eq!(round.round, 0);
+        request
+            .post(&format!("/api/bags/{}/rounds/advance", bag.id))
+            .add_header(player_key, player_value)
+            .await
+            .assert_status(axum::http::StatusCode::FORBIDDEN);
+
+        let advanced = request
+            .post(&format!("/api/bags/{}/rounds/advance", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        advanced.assert_status_ok();
+        let summary: interface::RoundSummary = advanced.json();
+        assert_eq!(summary.round.round, 1);
+        assert!(summary.active.is_empty());
+        assert_eq!(summary.expired.len(), 1);
+        assert_eq!(summary.expired[0].taken.id, taken.id);
+        assert_eq!(summary.expired[0].item.name, "Torch");
+
+        let undo = request
+            .post(&format!("/api/bags/{}/taken/undo", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(undo.status_code(), 409);
+        assert!(undo.text().contains("round_advanced"));
+
+        let round: interface::GameRound = request
+            .get(&format!("/api/bags/{}/rounds", bag.id))
+            .add_header(auth_key, auth_value)
+            .await
+            .json();
+        assert_eq!(round, summary.round);
+    }).await;
+}
@@ -0,0 +1,147 @@
+use roadiebag2::app::App;
+use loco_rs::testing;
+use serial_test::serial;
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn tag_crud() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let create_response = request
+            .post(&format!("/api/bags/{}/tags", bag.id))
+            .json(&interface::CreateUpdateTag { name: "weapon".to_string() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        create_response.assert_status_ok();
+        let tag: interface::Tag = create_response.json();
+        assert_eq!(tag.name, "weapon");
+        assert_eq!(tag.bag_id, bag.id);
+
+        // names are unique within a bag
+        request
+            .post(&format!("/api/bags/{}/tags", bag.id))
+            .json(&interface::CreateUpdateTag { name: "weapon".to_string() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_bad_request();
+
+        let renamed: interface::Tag = request
+            .post(&format!("/api/bags/{}/tags/{}", bag.id, tag.id))
+            .json(&interface::CreateUpdateTag { name: "melee".to_string() })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(renamed.name, "melee");
+
+        let read: interface::Tag = request
+            .get(&format!("/api/bags/{}/tags/{}", bag.id, tag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(read.name, "melee");
+
+        request
+            .delete(&format!("/api/bags/{}/tags/{}", bag.id, tag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+
+        let tags: Vec<interface::Tag> = request
+            .get(&format!("/api/bags/{}/tags", bag.id))
+            .add_header(auth_key, auth_value)
+            .await
+            .json();
+        assert!(tags.is_empty());
+    })
+        .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn filter_items_and_draws_by_tag() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let items = [
+            ("Sword", vec!["weapon", "metal"]),
+            ("Shield", vec!["armor", "metal"]),
+            ("Potion", vec!["consumable"]),
+        ];
+        for (name, tags) in items {
+            let item: interface::Item = request
+                .post(&format!("/api/bags/{}/items", bag.id))
+                .json(&interface::CreateUpdateItem {
+                    name: name.to_string(),
+                    tags: tags.iter().map(ToString::to_string).collect(),
+                    ..Default::default()
+                })
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            let mut expected = tags.clone();
+            expected.sort_unstable();
+            assert_eq!(item.tags, expected);
+        }
+
+        // tags are created on demand, once per bag
+        let tags: Vec<interface::Tag> = request
+            .get(&format!("/api/bags/{}/tags", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        let tag_names = tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(tag_names, ["armor", "consumable", "metal", "weapon"]);
+
+        let names = |page: &interface::ItemPage| {
+            let mut names = page.items.iter().map(|item| item.name.clone()).collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        let metal: interface::ItemPage = request
+            .get(&format!("/api/bags/{}/items", bag.id))
+            .add_query_param("tags", "metal")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(names(&metal), ["Shield", "Sword"]);
+
+        let not_armor: interface::ItemPage = request
+            .get(&format!("/api/bags/{}/items", bag.id))
+            .add_query_param("tags", "metal,consumable")
+            .add_query_param("exclude_tags", "armor")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(names(&not_armor), ["Potion", "Sword"]);
+
+        let drawn: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_query_param("tags", "weapon")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        let sword = metal.items.iter().find(|item| item.name == "Sword").unwrap();
+        assert_eq!(drawn.item_id, sword.id);
+        request
+            .post(&format!("/api/bags/{}/taken/done", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+
+        // the only weapon is gone now
+        request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_query_param("tags", "weapon")
+            .add_header(auth_key, auth_value)
+            .await
+            .assert_status(axum::http::StatusCode::CONFLICT);
+    })
+        .await;
+}
@@ -1,7 +1,10 @@
-use insta::{assert_debug_snapshot, with_settings};
+use axum_test::{TestServer, TestServerConfig};
+use insta::assert_debug_snapshot;
 use roadiebag2::app::App;
 use loco_rs::testing;
 use serial_test::serial;
+use roadiebag2::models::_entities::taken_items;
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
 use super::prepare_data;
 
 macro_rules! configure_insta {
@@ -20,43 +23,43 @@ async fn taken_crud() {
 
     testing::request::<App, _, _>(|request, ctx| async move {
         let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
 
         let create = interface::CreateUpdateItem {
             name: "Test item".to_string(),
-            description: None,
-            quantity: 1,
-            size: interface::ItemSize::Small,
-            infinite: false
+            ..Default::default()
         };
 
         let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
         let create_response = request
-            .post("/api/items")
+            .post(&format!("/api/bags/{}/items", bag.id))
             .json(&create)
             .add_header(auth_key.clone(), auth_value.clone())
             .await;
         create_response.assert_status_ok();
 
         let current_response = request
-            .get("/api/taken")
+            .get(&format!("/api/bags/{}/taken", bag.id))
             .add_header(auth_key.clone(), auth_value.clone())
             .await;
         current_response.assert_status_ok();
 
         let get_random = request
-            .post("/api/taken")
+            .post(&format!("/api/bags/{}/taken", bag.id))
             .add_header(auth_key.clone(), auth_value.clone())
             .await;
         get_random.assert_status_ok();
 
         let decr = request
-            .post("/api/taken/decrement")
+            .post(&format!("/api/bags/{}/taken/decrement", bag.id))
             .add_header(auth_key.clone(), auth_value.clone())
             .await;
         decr.assert_status_ok();
+        let decremented: interface::TakenItem = decr.json();
+        assert_eq!(decremented.status == interface::TakenStatus::Done, decremented.rounds_left == 0);
 
         let done_request = request
-            .post("/api/taken/done")
+            .post(&format!("/api/bags/{}/taken/done", bag.id))
             .add_header(auth_key.clone(), auth_value.clone())
             .await;
         done_request.assert_status_ok();
@@ -65,8 +68,9 @@ async fn taken_crud() {
             filters => {
                 let mut fvec = testing::CLEANUP_DATE.to_vec();
                 fvec.extend(vec![
-                    (r"rounds_left: \d", "rounds_left: ROUNDS_LEFT"),
-                    (r"rounds_total: \d", "rounds_total: ROUNDS_TOTAL")
+                    (r#"rounds_left\\":\d+"#, r#"rounds_left\":ROUNDS_LEFT"#),
+                    (r#"rounds_total\\":\d+"#, r#"rounds_total\":ROUNDS_TOTAL"#),
+                    (r#"status\\":\\"\w+\\""#, r#"status\":STATUS"#)
                 ]);
                 fvec
             }
@@ -80,4 +84,488 @@ async fn taken_crud() {
         });
 
     }).await;
-}
\ No newline at end of file
+}
+#[tokio::test]
+#[serial]
+async fn draw_from_empty_bag_conflicts() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let get_random = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(auth_key, auth_value)
+            .await;
+
+        assert_eq!(get_random.status_code(), 409);
+        let body: interface::BagExhausted = get_random.json();
+        assert_eq!(body.bag_id, bag.id);
+        assert_debug_snapshot!(body);
+    }).await;
+}
+
+#[tokio::test]
+#[serial]
+async fn concurrent_draws_share_one_active_draw() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let router = boot.router.unwrap();
+    let ctx = boot.app_context;
+    // a test server handles one request at a time, so every concurrent
+    // client gets its own server for the same app
+    let servers: Vec<TestServer> = (0..4)
+        .map(|_| {
+            let config = TestServerConfig::builder()
+                .default_content_type("application/json")
+                .build();
+            TestServer::new_with_config(router.clone(), config).unwrap()
+        })
+        .collect();
+    let request = &servers[0];
+
+    let user = prepare_data::init_user_login(request, &ctx).await;
+    let bag = prepare_data::init_bag(request, &user).await;
+    let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+    for name in ["Torch", "Rope", "Rations"] {
+        request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&interface::CreateUpdateItem {
+                name: name.to_string(),
+                quantity: 5,
+                ..Default::default()
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+    }
+
+    let draw = |server: &TestServer| server
+        .post(&format!("/api/bags/{}/taken", bag.id))
+        .add_header(auth_key.clone(), auth_value.clone());
+    let responses = tokio::join!(
+        draw(&servers[0]),
+        draw(&servers[1]),
+        draw(&servers[2]),
+        draw(&servers[3])
+    );
+
+    let drawn: Vec<interface::TakenItem> = [responses.0, responses.1, responses.2, responses.3]
+        .iter()
+        .map(|response| {
+            response.assert_status_ok();
+            response.json()
+        })
+        .collect();
+    assert!(drawn.iter().all(|taken| taken.id == drawn[0].id), "{drawn:?}");
+
+    let active = taken_items::Entity::find()
+        .filter(taken_items::Column::BagId.eq(bag.id))
+        .filter(taken_items::Column::Done.eq(false))
+        .count(&ctx.db)
+        .await
+        .unwrap();
+    assert_eq!(active, 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn history_lists_past_draws() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &owner).await;
+        let player = prepare_data::join_bag(&request, &ctx, &owner, &bag, "player@loco.com", interface::BagRole::Player).await;
+
+        let mut item_ids = vec![];
+        for name in ["Torch", "Rope"] {
+            let (auth_key, auth_value) = prepare_data::auth_header(&owner.token);
+            let item: interface::Item = request
+                .post(&format!("/api/bags/{}/items", bag.id))
+                .json(&interface::CreateUpdateItem {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+                .add_header(auth_key, auth_value)
+                .await
+                .json();
+            item_ids.push(item.id);
+        }
+
+        let mut drawn = vec![];
+        for user in [&owner, &player] {
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+            let taken: interface::TakenItem = request
+                .post(&format!("/api/bags/{}/taken", bag.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            request
+                .post(&format!("/api/bags/{}/taken/done", bag.id))
+                .add_header(auth_key, auth_value)
+                .await
+                .assert_status_ok();
+            drawn.push(taken);
+        }
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&player.token);
+        let history_response = request
+            .get(&format!("/api/bags/{}/taken/history", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        history_response.assert_status_ok();
+        let history: interface::TakenItemHistory = history_response.json();
+        assert_eq!(history.total_results, 2);
+        // newest first
+        let entry = &history.taken_history[0];
+        assert_eq!(entry.taken.id, drawn[1].id);
+        assert_eq!(entry.item.id, drawn[1].item_id);
+        assert_eq!(entry.taken.status, interface::TakenStatus::Done);
+        assert_eq!(entry.drawn_by.as_ref().unwrap().user_pid, player.user.pid.to_string());
+        assert_eq!(
+            history.taken_history[1].drawn_by.as_ref().unwrap().user_pid,
+            owner.user.pid.to_string()
+        );
+
+        let by_item: interface::TakenItemHistory = request
+            .get(&format!("/api/bags/{}/taken/history", bag.id))
+            .add_query_param("item_id", item_ids[0])
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(by_item.total_results, 1);
+        assert_eq!(by_item.taken_history[0].item.id, item_ids[0]);
+
+        let before: interface::TakenItemHistory = request
+            .get(&format!("/api/bags/{}/taken/history", bag.id))
+            .add_query_param("to", "2020-01-01T00:00:00")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(before.total_results, 0);
+
+        let since: interface::TakenItemHistory = request
+            .get(&format!("/api/bags/{}/taken/history", bag.id))
+            .add_query_param("from", "2020-01-01T00:00:00")
+            .add_query_param("page_size", 1)
+            .add_query_param("page_num", 1)
+            .add_header(auth_key, auth_value)
+            .await
+            .json();
+        assert_eq!(since.total_pages, 2);
+        assert_eq!(since.taken_history.len(), 1);
+        assert_eq!(since.taken_history[0].taken.id, drawn[0].id);
+    }).await;
+}
+
+#[tokio::test]
+#[serial]
+async fn hand_of_draws() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let router = boot.router.unwrap();
+    let ctx = boot.app_context;
+    let servers: Vec<TestServer> = (0..4)
+        .map(|_| {
+            let config = TestServerConfig::builder()
+                .default_content_type("application/json")
+                .build();
+            TestServer::new_with_config(router.clone(), config).unwrap()
+        })
+        .collect();
+    let request = &servers[0];
+
+    let user = prepare_data::init_user_login(request, &ctx).await;
+    let bag = prepare_data::init_bag(request, &user).await;
+    let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+    let bag: interface::Bag = request
+        .post(&format!("/api/bags/{}", bag.id))
+        .json(&interface::CreateUpdateBag {
+            name: bag.name,
+            draw_strategy: None,
+            auto_restock: false,
+            rounds_dice: None,
+            hand_size: Some(2),
+            reroll_budget: None,
+        })
+        .add_header(auth_key.clone(), auth_value.clone())
+        .await
+        .json();
+    assert_eq!(bag.hand_size, Some(2));
+
+    for name in ["Torch", "Rope", "Rations"] {
+        request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&interface::CreateUpdateItem {
+                name: name.to_string(),
+                quantity: 5,
+                rounds: Some(interface::RoundRange { min: 3, max: 3 }),
+                ..Default::default()
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+    }
+
+    // concurrent draws fill the hand without overfilling it
+    let draw = |server: &TestServer| server
+        .post(&format!("/api/bags/{}/taken", bag.id))
+        .add_header(auth_key.clone(), auth_value.clone());
+    let responses = tokio::join!(
+        draw(&servers[0]),
+        draw(&servers[1]),
+        draw(&servers[2]),
+        draw(&servers[3])
+    );
+    for response in [responses.0, responses.1, responses.2, responses.3] {
+        response.assert_status_ok();
+    }
+
+    let hand: Vec<interface::TakenItem> = request
+        .get(&format!("/api/bags/{}/taken", bag.id))
+        .add_header(auth_key.clone(), auth_value.clone())
+        .await
+        .json();
+    assert_eq!(hand.len(), 2);
+
+    let decremented: interface::TakenItem = request
+        .post(&format!("/api/bags/{}/taken/{}/decrement", bag.id, hand[1].id))
+        .add_header(auth_key.clone(), auth_value.clone())
+        .await
+        .json();
+    assert_eq!((decremented.id, decremented.rounds_left), (hand[1].id, 2));
+
+    let done = request
+        .post(&format!("/api/bags/{}/taken/{}/done", bag.id, hand[0].id))
+        .add_header(auth_key.clone(), auth_value.clone())
+        .await;
+    done.assert_status_ok();
+    assert_eq!(done.json::<interface::TakenItem>().status, interface::TakenStatus::Done);
+    request
+        .post(&format!("/api/bags/{}/taken/{}/done", bag.id, hand[0].id))
+        .add_header(auth_key.clone(), auth_value.clone())
+        .await
+        .assert_status_not_ok();
+
+    let summary: interface::RoundSummary = request
+        .post(&format!("/api/bags/{}/rounds/advance", bag.id))
+        .add_header(auth_key.clone(), auth_value.clone())
+        .await
+        .json();
+    let rounds: Vec<_> = summary.active.iter().map(|taken| (taken.id, taken.rounds_left)).collect();
+    assert_eq!(rounds, [(hand[1].id, 1)]);
+}
+
+#[tokio::test]
+#[serial]
+async fn undo_mistaken_done() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&interface::CreateUpdateItem {
+                name: "Torch".to_string(),
+                rounds: Some(interface::RoundRange { min: 3, max: 3 }),
+                ..Default::default()
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+        let drawn: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        request
+            .post(&format!("/api/bags/{}/taken/done", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+
+        let undo = request
+            .post(&format!("/api/bags/{}/taken/undo", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        undo.assert_status_ok();
+        let undone: interface::DrawTransition = undo.json();
+        assert_eq!(undone.action, interface::AuditAction::Done);
+        assert_eq!(undone.taken, [interface::TakenItem { updated_at: undone.taken[0].updated_at, ..drawn.clone() }]);
+        let hand: Vec<interface::TakenItem> = request
+            .get(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(hand[0].id, drawn.id);
+
+        let redone: interface::DrawTransition = request
+            .post(&format!("/api/bags/{}/taken/redo", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(redone.taken[0].status, interface::TakenStatus::Done);
+
+        let redo_again = request
+            .post(&format!("/api/bags/{}/taken/redo", bag.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(redo_again.status_code(), 409);
+        assert!(redo_again.text().contains("nothing_to_redo"));
+    }).await;
+}
+
+#[tokio::test]
+#[serial]
+async fn pause_resume_and_extend_draws() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&interface::CreateUpdateItem {
+                name: "Torch".to_string(),
+                rounds: Some(interface::RoundRange { min: 2, max: 2 }),
+                ..Default::default()
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+        let drawn: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(drawn.status, interface::TakenStatus::Active);
+
+        let paused: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken/{}/pause", bag.id, drawn.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(paused.status, interface::TakenStatus::Paused);
+        let summary: interface::RoundSummary = request
+            .post(&format!("/api/bags/{}/rounds/advance", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert!(summary.active.is_empty() && summary.expired.is_empty());
+
+        let extended: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken/{}/rounds", bag.id, drawn.id))
+            .json(&interface::AdjustRounds { delta: 2 })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(extended.rounds_left, 4);
+        let no_change = request
+            .post(&format!("/api/bags/{}/taken/{}/rounds", bag.id, drawn.id))
+            .json(&interface::AdjustRounds { delta: 0 })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(no_change.status_code(), 422);
+
+        let resumed: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken/{}/resume", bag.id, drawn.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!((resumed.status, resumed.rounds_left), (interface::TakenStatus::Active, 4));
+        request
+            .post(&format!("/api/bags/{}/taken/{}/resume", bag.id, drawn.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_not_ok();
+
+        // pausing is undone like any other change to a draw
+        let undone: interface::DrawTransition = request
+            .post(&format!("/api/bags/{}/taken/undo", bag.id))
+            .add_header(auth_key, auth_value)
+            .await
+            .json();
+        assert_eq!(undone.action, interface::AuditAction::Resume);
+        assert_eq!(undone.taken[0].status, interface::TakenStatus::Paused);
+    }).await;
+}
+
+#[tokio::test]
+#[serial]
+async fn reroll_within_budget() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let bag = prepare_data::init_bag(&request, &user).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let bag: interface::Bag = request
+            .post(&format!("/api/bags/{}", bag.id))
+            .json(&interface::CreateUpdateBag {
+                name: bag.name,
+                draw_strategy: None,
+                auto_restock: false,
+                rounds_dice: None,
+                hand_size: None,
+                reroll_budget: Some(1),
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!(bag.reroll_budget, Some(1));
+
+        request
+            .post(&format!("/api/bags/{}/items", bag.id))
+            .json(&interface::CreateUpdateItem {
+                name: "Torch".to_string(),
+                ..Default::default()
+            })
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .assert_status_ok();
+        let drawn: interface::TakenItem = request
+            .post(&format!("/api/bags/{}/taken", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+
+        // the only item goes back into the bag and is drawn again
+        let reroll = request
+            .post(&format!("/api/bags/{}/taken/{}/reroll", bag.id, drawn.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        reroll.assert_status_ok();
+        let rerolled: interface::TakenItem = reroll.json();
+        assert_eq!(rerolled.item_id, drawn.item_id);
+        assert_ne!(rerolled.id, drawn.id);
+
+        let spent = request
+            .post(&format!("/api/bags/{}/taken/{}/reroll", bag.id, rerolled.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(spent.status_code(), 409);
+        assert!(spent.text().contains("reroll_budget_spent"));
+
+        // a new game comes with the whole budget again
+        let game: interface::GameRound = request
+            .post(&format!("/api/bags/{}/rounds/new-game", bag.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .json();
+        assert_eq!((game.game, game.round), (2, 0));
+        let reroll = request
+            .post(&format!("/api/bags/{}/taken/{}/reroll", bag.id, rerolled.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        reroll.assert_status_ok();
+
+        let history: interface::TakenItemHistory = request
+            .get(&format!("/api/bags/{}/taken/history", bag.id))
+            .add_header(auth_key, auth_value)
+            .await
+            .json();
+        let statuses: Vec<_> = history.taken_history.iter().map(|entry| entry.taken.status).collect();
+        assert_eq!(statuses, [interface::TakenStatus::Active, interface::TakenStatus::Rerolled, interface::TakenStatus::Rerolled]);
+    }).await;
+}
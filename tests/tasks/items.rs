@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+
+use loco_rs::{boot::run_task, testing};
+use roadiebag2::{app::App, models::{bags, items}};
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn test_can_import_and_export_items() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let bag = bags::Model::create(
+        &boot.app_context.db,
+        1,
+        interface::CreateUpdateBag {
+            name: "Test bag".to_string(),
+            draw_strategy: None,
+            auto_restock: false,
+            rounds_dice: None,
+            hand_size: None,
+            reroll_budget: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let dir = std::env::temp_dir();
+    let import_file = dir.join("roadiebag2_import_items.yaml");
+    let export_file = dir.join("roadiebag2_export_items.csv");
+    std::fs::write(&import_file, "- name: Rope\n  quantity: 3\n  size: Medium\n  infinite: false\n  tags: [tool]\n").unwrap();
+
+    let vars = |file: &std::path::Path| BTreeMap::from([
+        ("bag".to_string(), bag.id.to_string()),
+        ("file".to_string(), file.display().to_string()),
+    ]);
+    run_task::<App>(&boot.app_context, Some(&"import_items".to_string()), &vars(&import_file))
+        .await
+        .unwrap();
+    run_task::<App>(&boot.app_context, Some(&"export_items".to_string()), &vars(&export_file))
+        .await
+        .unwrap();
+
+    let exported = items::Model::export(&boot.app_context.db, bag.id).await.unwrap();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].tags, ["tool"]);
+    assert_eq!(
+        std::fs::read_to_string(&export_file).unwrap(),
+        "name,description,quantity,size,infinite,weight,tags,min_rounds,max_rounds,rounds_dice\nRope,,3,Medium,false,1,tool,,,\n"
+    );
+}
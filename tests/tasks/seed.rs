@@ -1,42 +1,47 @@
-//! This task implements data seeding functionality for initializing new
-//! development/demo environments.
-//!
-//! # Example
-//!
-//! Run the task with the following command:
-//! ```sh
-//! cargo run task
-//! ```
-//!
-//! To override existing data and reset the data structure, use the following
-//! command with the `refresh:true` argument:
-//! ```sh
-//! cargo run task seed_data refresh:true
-//! ```
 use std::collections::BTreeMap;
 
-use loco_rs::{db, prelude::*};
-use roadiebag2::app::App;
-use migration::Migrator;
-
-#[allow(clippy::module_name_repetitions)]
-pub struct SeedData;
-#[async_trait]
-impl Task for SeedData {
-    fn task(&self) -> TaskInfo {
-        TaskInfo {
-            name: "seed_data".to_string(),
-            detail: "Task for seeding data".to_string(),
-        }
-    }
-    async fn run(&self, app_context: &AppContext, vars: &BTreeMap<String, String>) -> Result<()> {
-        let refresh = vars.get("refresh").is_some_and(|refresh| refresh == "true");
-
-        if refresh {
-            db::reset::<Migrator>(&app_context.db).await?;
-        }
-        let path = std::path::Path::new("src/fixtures");
-        db::run_app_seed::<App>(&app_context.db, path).await?;
-        Ok(())
-    }
+use loco_rs::{boot::run_task, testing};
+use roadiebag2::{app::App, models::{bags, items, taken_items}};
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn test_can_seed_data() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+
+    assert!(run_task::<App>(
+        &boot.app_context,
+        Some(&"seed_data".to_string()),
+        &BTreeMap::new()
+    )
+    .await
+    .is_ok());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_can_seed_demo_bag() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+
+    run_task::<App>(
+        &boot.app_context,
+        Some(&"seed_data".to_string()),
+        &BTreeMap::from([("demo".to_string(), "true".to_string())]),
+    )
+    .await
+    .unwrap();
+
+    // the fixture bag and the demo bag
+    let bags = bags::Model::list_for_user(&boot.app_context.db, 1).await.unwrap();
+    assert_eq!(bags.len(), 2);
+
+    let fixture = items::Model::list(&boot.app_context.db, bags[0].id, None).await.unwrap();
+    assert_eq!(fixture.total_results, 4);
+    let history = taken_items::Model::history(&boot.app_context.db, bags[0].id, None).await.unwrap();
+    assert_eq!(history.total_results, 2);
+
+    let demo = items::Model::list(&boot.app_context.db, bags[1].id, None).await.unwrap();
+    assert!(demo.total_results >= 8);
+    let current = taken_items::Model::get_current(&boot.app_context.db, bags[1].id).await.unwrap();
+    assert!(current.is_some());
 }